@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use super::registry::{Action, ActionContext, ActionOption, ActionOutcome, ActionRegistry, PickerItem, Selection};
+use crate::config::{config_path, Config, LoadIssue};
+use crate::history;
+use crate::translation::{self, ProviderKind, TranslationConfig, Translator};
+
+pub fn register_builtins(registry: &mut ActionRegistry, config: &Config) -> Vec<LoadIssue> {
+    let mut issues = Vec::new();
+    registry.register(Box::new(CopyAction));
+    registry.register(Box::new(CutAction));
+    registry.register(Box::new(PasteAction));
+    if crate::history::is_enabled() {
+        registry.register(Box::new(HistoryAction));
+    }
+    super::search::register_search_engines(registry, &config.search, &mut issues);
+
+    let translator = translation::from_config(&config.translation).unwrap_or_else(|e| {
+        issues.push(LoadIssue::new(config_path(), format!("translation: {}; falling back to Google", e)));
+        translation::build(ProviderKind::Google, &config.translation).expect("Google needs no credentials")
+    });
+    let (cache, cache_issue) = translation::open_cache(&config.translation.cache);
+    if let Some(issue) = cache_issue {
+        issues.push(LoadIssue::new(config_path(), issue));
+    }
+    let glossary = translation::load_glossary(&config.translation).unwrap_or_else(|e| {
+        issues.push(LoadIssue::new(config_path(), format!("translation.glossary: {}", e)));
+        None
+    });
+    // Glossary outside the cache: masked text is what gets cached
+    let wrap = |translator| translation::with_glossary(translation::with_cache(translator, cache.as_ref()), glossary.as_ref());
+    let translator = wrap(translator);
+    registry.register(Box::new(TranslateAction { translator: translator.clone(), config: config.translation.clone() }));
+    registry.register(Box::new(RoundTripAction { translator, config: config.translation.clone() }));
+
+    let mut translators = Vec::new();
+    for kind in config.translation.compare_providers() {
+        match translation::build(kind, &config.translation) {
+            Ok(translator) => {
+                translators.push((kind.label().to_string(), wrap(translator)));
+            }
+            Err(e) => {
+                issues.push(LoadIssue::new(config_path(), format!("translation.compare: {}: {}", kind.label(), e)));
+            }
+        }
+    }
+    if translators.len() > 1 {
+        registry.register(Box::new(CompareAction { translators, config: config.translation.clone() }));
+    }
+    super::prompt::register_prompts(registry, &config.llm, &mut issues);
+    issues
+}
+
+pub struct CopyAction;
+
+impl Action for CopyAction {
+    fn id(&self) -> &str { "copy" }
+    fn label(&self) -> &str { "Copy" }
+    fn icon(&self) -> &str { "📋" }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        super::copy_selection(&ctx.selection.text, &ctx.selection.app);
+        ActionOutcome::Dismiss
+    }
+}
+
+pub struct CutAction;
+
+impl Action for CutAction {
+    fn id(&self) -> &str { "cut" }
+    fn label(&self) -> &str { "Cut" }
+    fn icon(&self) -> &str { "✂️" }
+
+    fn run(&self, _ctx: &ActionContext) -> ActionOutcome {
+        super::cut();
+        ActionOutcome::Dismiss
+    }
+}
+
+pub struct PasteAction;
+
+impl Action for PasteAction {
+    fn id(&self) -> &str { "paste" }
+    fn label(&self) -> &str { "Paste" }
+    fn icon(&self) -> &str { "📄" }
+
+    // Pasting replaces the selection, so it makes sense even for whitespace-only text
+    fn is_available(&self, _selection: &Selection) -> bool {
+        true
+    }
+
+    fn run(&self, _ctx: &ActionContext) -> ActionOutcome {
+        super::paste();
+        ActionOutcome::Dismiss
+    }
+}
+
+// Clipboard history picker; choosing an entry pastes it over the selection
+pub struct HistoryAction;
+
+impl Action for HistoryAction {
+    fn id(&self) -> &str { "history" }
+    fn label(&self) -> &str { "History" }
+    fn icon(&self) -> &str { "📜" }
+
+    fn is_available(&self, _selection: &Selection) -> bool {
+        true
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        if let Some(entry) = ctx.option.and_then(|id| id.parse().ok()).and_then(history::find) {
+            super::copy_selection(&entry.text, &ctx.selection.app);
+            super::paste();
+            return ActionOutcome::Dismiss;
+        }
+        let items = history::entries()
+            .into_iter()
+            .map(|entry| PickerItem {
+                action_id: "history".to_string(),
+                option: Some(entry.id.to_string()),
+                label: preview(&entry.text),
+                keyword: String::new(),
+                detail: match &entry.source_app {
+                    Some(app) => format!("{} · {}", app, history::format_age(entry.copied_at)),
+                    None => history::format_age(entry.copied_at),
+                },
+                search_text: entry.text,
+            })
+            .collect();
+        ActionOutcome::Pick(items)
+    }
+}
+
+// First line of an entry, shortened for the picker
+fn preview(text: &str) -> String {
+    const MAX_CHARS: usize = 40;
+    let first_line = text.trim().lines().next().unwrap_or_default();
+    let mut preview: String = first_line.chars().take(MAX_CHARS).collect();
+    if first_line.chars().count() > MAX_CHARS || text.trim().lines().nth(1).is_some() {
+        preview.push('…');
+    }
+    preview
+}
+
+pub struct TranslateAction {
+    translator: Arc<dyn Translator>,
+    config: TranslationConfig,
+}
+
+impl Action for TranslateAction {
+    fn id(&self) -> &str { "translate" }
+    fn label(&self) -> &str { "EN" }
+    fn icon(&self) -> &str { "A文" }
+
+    // The configured languages, so one click can override target/auto-swap
+    fn options(&self) -> Vec<ActionOption> {
+        language_options(&self.config)
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        let mut request = self.config.request(&ctx.selection.text, ctx.option);
+        request.bypass_cache = ctx.force_refresh;
+        request.cancel = ctx.cancel.clone();
+        super::translate_async(self.translator.clone(), request, self.config.max_parallel, ctx.responder());
+        ActionOutcome::Pending("翻訳中...".to_string())
+    }
+}
+
+// Translate and translate back, to sanity-check outgoing text
+pub struct RoundTripAction {
+    translator: Arc<dyn Translator>,
+    config: TranslationConfig,
+}
+
+impl Action for RoundTripAction {
+    fn id(&self) -> &str { "translate.roundtrip" }
+    fn label(&self) -> &str { "Check" }
+    fn icon(&self) -> &str { "↺" }
+
+    fn options(&self) -> Vec<ActionOption> {
+        language_options(&self.config)
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        let mut request = self.config.request(&ctx.selection.text, ctx.option);
+        request.bypass_cache = ctx.force_refresh;
+        request.cancel = ctx.cancel.clone();
+        super::round_trip_async(self.translator.clone(), request, self.config.max_parallel, ctx.responder());
+        ActionOutcome::Pending("翻訳して逆翻訳中...".to_string())
+    }
+}
+
+// Same selection through several providers at once; results appear side by side as they arrive
+pub struct CompareAction {
+    translators: Vec<(String, Arc<dyn Translator>)>,
+    config: TranslationConfig,
+}
+
+impl Action for CompareAction {
+    fn id(&self) -> &str { "translate.compare" }
+    fn label(&self) -> &str { "Compare" }
+    fn icon(&self) -> &str { "⚖" }
+
+    fn options(&self) -> Vec<ActionOption> {
+        language_options(&self.config)
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        let mut request = self.config.request(&ctx.selection.text, ctx.option);
+        request.bypass_cache = ctx.force_refresh;
+        request.cancel = ctx.cancel.clone();
+        super::compare_async(&self.translators, request, self.config.max_parallel, ctx.responder());
+        ActionOutcome::Pending("比較中...".to_string())
+    }
+}
+
+fn language_options(config: &TranslationConfig) -> Vec<ActionOption> {
+    config
+        .languages
+        .iter()
+        .map(|lang| ActionOption { id: lang.clone(), label: format!("→ {}", lang.to_uppercase()) })
+        .collect()
+}
@@ -1,104 +1,213 @@
-use arboard::Clipboard;
-use crossbeam_channel::Sender;
-use crate::AppEvent;
+use std::sync::Arc;
 
-pub fn copy_selection(text: &str) {
-    if let Ok(mut clipboard) = Clipboard::new() {
-        let _: Result<(), _> = clipboard.set_text(text);
+use crate::automation::AppInfo;
+use crate::platform::{self, Shortcut};
+use crate::AppEvent;
+use crate::translation::{
+    self, translate_chunked, RoundTrip, TranslationError, TranslationRequest, TranslationResult, Translator,
+};
+
+mod builtin;
+mod pack;
+mod process;
+mod prompt;
+mod registry;
+mod search;
+
+pub use pack::{load_packs, PackReport};
+pub use process::{run as run_process, ProcessError, ProcessSpec};
+pub use search::SearchEngine;
+pub use registry::{
+    ActionContext, ActionOutcome, ActionRegistry, CancelToken, OutputMode, PickerItem, RequestId, Responder, Selection,
+};
+
+// `app` is where the selection came from; by the time this runs the focus may be on PopWin itself
+pub fn copy_selection(text: &str, app: &AppInfo) {
+    if let Err(e) = platform::with_clipboard(|clipboard| clipboard.set_text(text)) {
+        log::warn!("Copy failed: {}", e);
     }
+    crate::history::record(text, app);
 }
 
 pub fn paste() {
-    // Simulate Ctrl+V
-    simulate_ctrl_v();
+    platform::injector().send(Shortcut::Paste);
 }
 
 pub fn cut() {
-    // Simulate Ctrl+X
-    simulate_ctrl_x();
-}
-
-pub fn search_perplexity(text: &str) {
-    let url = format!("https://www.perplexity.ai/search?q={}", urlencoding::encode(text));
-    if let Err(e) = webbrowser::open(&url) {
-        log::error!("Failed to open browser: {}", e);
-    }
+    platform::injector().send(Shortcut::Cut);
 }
 
-pub fn translate_async(text: &str, sender: Sender<AppEvent>) {
+// Run a shell action on a worker thread and report its output through the event channel
+pub fn run_shell_async(
+    action_id: &str,
+    spec: ProcessSpec,
+    mode: OutputMode,
+    text: &str,
+    responder: Responder,
+) {
+    let action_id = action_id.to_string();
     let text = text.to_string();
     std::thread::spawn(move || {
-        let result = translate_with_google(&text);
-        let _ = sender.send(AppEvent::TranslationReceived(result));
-    });
-}
-
-fn translate_with_google(text: &str) -> String {
-    let client = reqwest::blocking::Client::new();
-    let url = "https://translate.googleapis.com/translate_a/single";
-    let params = [
-        ("client", "gtx"),
-        ("sl", "auto"),
-        ("tl", "ja"),
-        ("dt", "t"),
-        ("q", text),
-    ];
-
-    match client.get(url).query(&params).send() {
-        Ok(resp) => {
-            if resp.status().is_success() {
-                if let Ok(json) = resp.json::<serde_json::Value>() {
-                    // Navigate JSON: [[[ "翻訳結果", "Original", ...], ...], ...]
-                    if let Some(sentences) = json.as_array().and_then(|a| a.get(0)).and_then(|v| v.as_array()) {
-                        let mut result = String::new();
-                        for sentence in sentences {
-                            if let Some(s) = sentence.as_array().and_then(|a| a.get(0)).and_then(|v| v.as_str()) {
-                                result.push_str(s);
-                            }
-                        }
-                        if !result.is_empty() {
-                            return result;
-                        }
+        let result = match process::run(&spec, &text, &responder.cancel) {
+            // Replacing the selection with half an output would silently lose text
+            Ok(output) if output.truncated && mode != OutputMode::Show => {
+                Err(format!("output exceeded {} bytes", spec.max_output))
+            }
+            Ok(output) => {
+                if !output.stderr.trim().is_empty() {
+                    log::debug!("Action '{}' stderr: {}", action_id, output.stderr.trim());
+                }
+                let mut stdout = output.stdout;
+                // Line-oriented tools (sort, jq) add a final newline the selection didn't have
+                if !text.ends_with('\n') && stdout.ends_with('\n') {
+                    stdout.pop();
+                    if stdout.ends_with('\r') {
+                        stdout.pop();
                     }
                 }
+                if output.truncated {
+                    stdout.push_str("\n… (truncated)");
+                }
+                Ok(stdout)
+            }
+            Err(ProcessError::Cancelled) => {
+                log::info!("Action '{}' cancelled", action_id);
+                return;
+            }
+            Err(e) => {
+                log::warn!("Action '{}' failed: {}", action_id, e);
+                Err(e.to_string())
             }
-            "翻訳エラー".to_string()
+        };
+        let request_id = responder.request_id;
+        responder.send(AppEvent::ActionOutput { request_id, action_id, mode, result });
+    });
+}
+
+// The HTTP client's timeout bounds how long this thread lives; a reply that arrives
+// after the toolbar moved on is dropped by the responder
+pub fn translate_async(
+    translator: Arc<dyn Translator>,
+    request: TranslationRequest,
+    max_parallel: usize,
+    responder: Responder,
+) {
+    std::thread::spawn(move || {
+        if responder.is_cancelled() {
+            return;
         }
-        Err(e) => {
-            log::error!("Translation failed: {}", e);
-            "通信エラー".to_string()
+        let request_id = responder.request_id;
+        let event = match translate_reporting(&*translator, &request, max_parallel, &responder) {
+            Ok(translation) => AppEvent::TranslationReceived { request_id, translation },
+            Err(error) => AppEvent::TranslationFailed { request_id, error },
+        };
+        responder.send(event);
+    });
+}
+
+// Like translate_async, then translates the result back into the source language for comparison
+pub fn round_trip_async(
+    translator: Arc<dyn Translator>,
+    request: TranslationRequest,
+    max_parallel: usize,
+    responder: Responder,
+) {
+    std::thread::spawn(move || {
+        if responder.is_cancelled() {
+            return;
         }
+        let request_id = responder.request_id;
+        let result = translate_reporting(&*translator, &request, max_parallel, &responder).and_then(|translation| {
+            let source = request
+                .source
+                .clone()
+                .or_else(|| translation.detected_source.clone())
+                .or_else(|| translation::detect_language(&request.text).map(|s| s.to_string()))
+                .ok_or_else(|| TranslationError::Config("cannot tell the source language".to_string()))?;
+            let back_request = TranslationRequest {
+                text: translation.text.clone(),
+                source: Some(request.target.clone()),
+                target: source,
+                ..request.clone()
+            };
+            let back = translate_reporting(&*translator, &back_request, max_parallel, &responder)?;
+            Ok(RoundTrip::new(request.text.clone(), translation, back))
+        });
+        let event = match result {
+            Ok(round_trip) => AppEvent::RoundTripReceived { request_id, round_trip },
+            Err(error) => AppEvent::TranslationFailed { request_id, error },
+        };
+        responder.send(event);
+    });
+}
+
+// Chunked translation with progress events and error logging, shared by the async entry points
+fn translate_reporting(
+    translator: &dyn Translator,
+    request: &TranslationRequest,
+    max_parallel: usize,
+    responder: &Responder,
+) -> TranslationResult {
+    let request_id = responder.request_id;
+    let on_progress = |done, total| responder.send(AppEvent::TranslationProgress { request_id, done, total });
+    let result = translate_chunked(translator, request, max_parallel, &on_progress);
+    if let Err(error) = &result {
+        log::error!(
+            "Translation via {} failed ({} chars, {} -> {}): {}",
+            translator.id(),
+            request.text.chars().count(),
+            request.source.as_deref().unwrap_or("auto"),
+            request.target,
+            error
+        );
     }
+    result
 }
 
-// Deprecated synchronous dummy translation for reference
-pub fn translate(text: &str) -> String {
-    let text = text.trim();
-    if text.eq_ignore_ascii_case("hello") {
-        return "こんにちは (挨拶)".to_string();
-    } else if text.eq_ignore_ascii_case("simulation") {
-        return "シミュレーション (模擬実験)".to_string();
-    } else if text.eq_ignore_ascii_case("popwin") {
-        return "ポップウィン (このアプリ)".to_string();
-    } else if text.eq_ignore_ascii_case("rust") {
-        return "Rust (プログラミング言語)".to_string();
+// One thread per provider; each result is sent as soon as it arrives, in no particular order
+pub fn compare_async(
+    translators: &[(String, Arc<dyn Translator>)],
+    request: TranslationRequest,
+    max_parallel: usize,
+    responder: Responder,
+) {
+    let providers = translators.iter().map(|(label, _)| label.clone()).collect();
+    responder.send(AppEvent::ComparisonStarted { request_id: responder.request_id, providers });
+
+    for (label, translator) in translators {
+        let (label, translator, request, responder) = (label.clone(), translator.clone(), request.clone(), responder.clone());
+        std::thread::spawn(move || {
+            if responder.is_cancelled() {
+                return;
+            }
+            let result = translate_chunked(&*translator, &request, max_parallel, &|_, _| {});
+            if let Err(error) = &result {
+                log::error!("Comparison via {} failed: {}", translator.id(), error);
+            }
+            responder.send(AppEvent::ComparisonResult { request_id: responder.request_id, provider: label, result });
+        });
     }
-    
-    format!("翻訳(PoC): {} の日本語訳サンプル", text)
 }
 
 #[cfg(target_os = "windows")]
 mod windows_input {
     use windows::Win32::UI::Input::KeyboardAndMouse::{
-        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_V, VK_X, VK_CONTROL,
+        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_C, VK_V, VK_X, VK_CONTROL,
     };
 
-    pub fn simulate_ctrl_v() {
-        unsafe { send_combo(VK_V); }
-    }
+    use crate::platform::{InputInjector, Shortcut};
 
-    pub fn simulate_ctrl_x() {
-        unsafe { send_combo(VK_X); }
+    pub struct SendInputInjector;
+
+    impl InputInjector for SendInputInjector {
+        fn send(&self, shortcut: Shortcut) {
+            let key = match shortcut {
+                Shortcut::Copy => VK_C,
+                Shortcut::Cut => VK_X,
+                Shortcut::Paste => VK_V,
+            };
+            unsafe { send_combo(key); }
+        }
     }
 
     unsafe fn send_combo(key: windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY) {
@@ -112,19 +221,11 @@ mod windows_input {
     }
 }
 
-#[cfg(not(target_os = "windows"))]
-mod dummy_input {
-    pub fn simulate_ctrl_v() {
-        println!("Action: Paste (Simulated)");
-    }
-
-    pub fn simulate_ctrl_x() {
-        println!("Action: Cut (Simulated)");
-    }
-}
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11_input;
 
 #[cfg(target_os = "windows")]
-use windows_input::*;
+pub use windows_input::SendInputInjector;
 
-#[cfg(not(target_os = "windows"))]
-use dummy_input::*;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use x11_input::XTestInjector;
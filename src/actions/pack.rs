@@ -0,0 +1,437 @@
+// Declarative action packs (PopClip-style extensions).
+//
+// A pack is a TOML or JSON file in one of the pack directories:
+//
+//   name = "Team tools"
+//
+//   [[actions]]
+//   name = "Open ticket"
+//   icon = "🎫"
+//   filter = "^[A-Z]+-\\d+$"
+//   kind = "url"
+//   template = "https://jira.example.com/browse/{text}"
+//
+// `{text}` is the selection (URL-encoded for `url` actions). Shell commands get the
+// selection on stdin instead, so it is never spliced into a command line (and in $POPWIN_TEXT
+// when it is short, up to 16 KB, and has no NUL):
+//
+//   [[actions]]
+//   name = "Format JSON"
+//   kind = "shell"
+//   template = "jq ."
+//   output = "replace"   # show (default) | copy | replace
+//   timeout_ms = 5000
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use super::process::ProcessSpec;
+use super::registry::{Action, ActionContext, ActionOutcome, ActionRegistry, OutputMode, Selection};
+use crate::config::{describe_toml_error, LoadIssue};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackFile {
+    name: Option<String>,
+    #[serde(default)]
+    actions: Vec<ActionDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionDef {
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    icon: String,
+    filter: Option<String>,
+    kind: PackKind,
+    template: String,
+    output: Option<OutputMode>,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackKind {
+    Url,
+    Shell,
+    Transform,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadedPack {
+    pub path: PathBuf,
+    pub name: String,
+    pub action_count: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PackReport {
+    pub loaded: Vec<LoadedPack>,
+    pub issues: Vec<LoadIssue>,
+}
+
+pub struct PackAction {
+    id: String,
+    label: String,
+    icon: String,
+    filter: Option<Regex>,
+    kind: PackKind,
+    template: String,
+    output: OutputMode,
+    timeout: Duration,
+}
+
+impl Action for PackAction {
+    fn id(&self) -> &str { &self.id }
+    fn label(&self) -> &str { &self.label }
+    fn icon(&self) -> &str { &self.icon }
+
+    fn is_available(&self, selection: &Selection) -> bool {
+        if selection.is_empty() {
+            return false;
+        }
+        match &self.filter {
+            Some(filter) => filter.is_match(&selection.text),
+            None => true,
+        }
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        let text = &ctx.selection.text;
+        match self.kind {
+            PackKind::Url => {
+                let url = render_template(&self.template, &urlencoding::encode(text));
+                if let Err(e) = webbrowser::open(&url) {
+                    log::error!("Failed to open browser: {}", e);
+                }
+            }
+            PackKind::Shell => {
+                let spec = ProcessSpec { timeout: self.timeout, ..ProcessSpec::new(&self.template) };
+                super::run_shell_async(&self.id, spec, self.output, text, ctx.responder());
+                return ActionOutcome::Pending(format!("{} 実行中...", self.label));
+            }
+            PackKind::Transform => {
+                super::copy_selection(&render_template(&self.template, text), &ctx.selection.app);
+                super::paste();
+            }
+        }
+        ActionOutcome::Dismiss
+    }
+}
+
+// Load every pack in `dirs` (missing directories are skipped) and register the valid actions.
+// Problems are collected in the report instead of aborting, so one bad file can't break startup.
+pub fn load_packs(dirs: &[PathBuf], registry: &mut ActionRegistry) -> PackReport {
+    let mut report = PackReport::default();
+    let mut seen_ids = HashSet::new();
+
+    for dir in dirs {
+        if !dir.exists() {
+            continue;
+        }
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                report.issues.push(LoadIssue::new(dir, format!("cannot read directory: {}", e)));
+                continue;
+            }
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("toml") | Some("json")))
+            .collect();
+        files.sort();
+
+        for path in files {
+            match load_pack_file(&path) {
+                Ok((name, defs)) => {
+                    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("pack").to_string();
+                    let mut count = 0;
+                    for (index, def) in defs.into_iter().enumerate() {
+                        let label = def.name.clone();
+                        match build_action(&stem, def) {
+                            Ok(action) if !seen_ids.insert(action.id.clone()) => {
+                                report.issues.push(LoadIssue::new(
+                                    &path,
+                                    format!("actions[{}] ('{}'): duplicate id '{}'", index, label, action.id),
+                                ));
+                            }
+                            // Packs load after the built-ins, so this can only be a built-in
+                            Ok(action) if registry.get(&action.id).is_some() => {
+                                report.issues.push(LoadIssue::new(
+                                    &path,
+                                    format!("actions[{}] ('{}'): '{}' is a built-in action", index, label, action.id),
+                                ));
+                            }
+                            Ok(action) => {
+                                registry.register(Box::new(action));
+                                count += 1;
+                            }
+                            Err(message) => {
+                                report.issues.push(LoadIssue::new(
+                                    &path,
+                                    format!("actions[{}] ('{}'): {}", index, label, message),
+                                ));
+                            }
+                        }
+                    }
+                    report.loaded.push(LoadedPack { path, name: name.unwrap_or(stem), action_count: count });
+                }
+                Err(issue) => report.issues.push(issue),
+            }
+        }
+    }
+
+    for issue in &report.issues {
+        log::warn!("Action pack problem: {}", issue);
+    }
+    report
+}
+
+fn load_pack_file(path: &Path) -> Result<(Option<String>, Vec<ActionDef>), LoadIssue> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| LoadIssue::new(path, format!("cannot read file: {}", e)))?;
+
+    let pack: PackFile = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        serde_json::from_str(&content)
+            .map_err(|e| LoadIssue::new(path, e.to_string()))?
+    } else {
+        toml::from_str(&content).map_err(|e| LoadIssue::new(path, describe_toml_error(&content, &e)))?
+    };
+
+    if pack.actions.is_empty() {
+        return Err(LoadIssue::new(path, "pack defines no [[actions]]"));
+    }
+    Ok((pack.name, pack.actions))
+}
+
+fn build_action(pack_stem: &str, def: ActionDef) -> Result<PackAction, String> {
+    let name = def.name.trim();
+    if name.is_empty() {
+        return Err("`name` must not be empty".to_string());
+    }
+    if def.template.trim().is_empty() {
+        return Err("`template` must not be empty".to_string());
+    }
+
+    let filter = match def.filter.as_deref() {
+        Some(pattern) => Some(Regex::new(pattern).map_err(|e| format!("invalid `filter` regex: {}", e))?),
+        None => None,
+    };
+
+    // Braces are ordinary in shell commands (awk '{print $1}', jq '{name: .name}')
+    if def.kind == PackKind::Shell {
+        if def.template.contains("{text}") {
+            return Err("shell commands cannot use {text}; read the selection from stdin or $POPWIN_TEXT".to_string());
+        }
+    } else if let Some(placeholder) = placeholders(&def.template).into_iter().find(|p| *p != "text") {
+        return Err(format!("unknown placeholder '{{{}}}' in `template` (only {{text}} is supported)", placeholder));
+    }
+
+    if def.kind == PackKind::Url && !(def.template.starts_with("https://") || def.template.starts_with("http://")) {
+        return Err("url `template` must start with http:// or https://".to_string());
+    }
+
+    if def.kind != PackKind::Shell && (def.output.is_some() || def.timeout_ms.is_some()) {
+        return Err("`output` and `timeout_ms` only apply to shell actions".to_string());
+    }
+    if def.timeout_ms == Some(0) {
+        return Err("`timeout_ms` must be greater than 0".to_string());
+    }
+
+    let local_id = def.id.unwrap_or_else(|| slug(name));
+    if local_id.is_empty() {
+        return Err("cannot derive an id from `name`; set `id` explicitly".to_string());
+    }
+
+    Ok(PackAction {
+        id: format!("{}.{}", pack_stem, local_id),
+        label: name.to_string(),
+        icon: def.icon,
+        filter,
+        kind: def.kind,
+        template: def.template,
+        output: def.output.unwrap_or_default(),
+        timeout: def.timeout_ms.map(Duration::from_millis).unwrap_or(super::process::DEFAULT_TIMEOUT),
+    })
+}
+
+// One pass over the template, like `SearchEngine::url_for`: the selection is never scanned
+fn render_template(template: &str, text: &str) -> String {
+    let mut out = String::with_capacity(template.len() + text.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{text}") {
+        out.push_str(&rest[..start]);
+        out.push_str(text);
+        rest = &rest[start + "{text}".len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else { break };
+        found.push(&rest[start + 1..start + len]);
+        rest = &rest[start + len + 1..];
+    }
+    found
+}
+
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::search::register_search_engines;
+    use crate::config::SearchConfig;
+
+    // Load the given pack files next to the built-in search engines
+    fn load(files: &[(&str, &str)]) -> (ActionRegistry, PackReport) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.path().join(name), content).unwrap();
+        }
+        let mut registry = ActionRegistry::new();
+        register_search_engines(&mut registry, &SearchConfig::default(), &mut Vec::new());
+        let report = load_packs(&[dir.path().to_path_buf()], &mut registry);
+        (registry, report)
+    }
+
+    fn issues(report: &PackReport) -> Vec<&str> {
+        report.issues.iter().map(|issue| issue.message.as_str()).collect()
+    }
+
+    #[test]
+    fn valid_actions_are_registered_under_the_pack_name() {
+        let (registry, report) = load(&[(
+            "team.toml",
+            r#"
+            [[actions]]
+            name = "Open ticket"
+            kind = "url"
+            template = "https://jira.example.com/browse/{text}"
+
+            [[actions]]
+            id = "fmt"
+            name = "Format JSON"
+            kind = "shell"
+            template = "jq '{name: .name}'"
+            output = "replace"
+            "#,
+        )]);
+        assert!(report.issues.is_empty(), "{:?}", issues(&report));
+        assert_eq!(report.loaded[0].action_count, 2);
+        assert!(registry.get("team.open-ticket").is_some());
+        assert!(registry.get("team.fmt").is_some());
+    }
+
+    #[test]
+    fn duplicate_ids_keep_the_first_action() {
+        let action = "[[actions]]\nname = \"Upper\"\nkind = \"transform\"\ntemplate = \"{text}\"\n";
+        let (registry, report) = load(&[("a.toml", &format!("{0}{0}", action))]);
+        assert_eq!(issues(&report), ["actions[1] ('Upper'): duplicate id 'a.upper'"]);
+        assert_eq!(report.loaded[0].action_count, 1);
+        assert!(registry.get("a.upper").is_some());
+    }
+
+    #[test]
+    fn built_in_ids_are_not_replaced() {
+        let (registry, report) = load(&[(
+            "search.toml",
+            "[[actions]]\nid = \"google\"\nname = \"G\"\nkind = \"url\"\ntemplate = \"https://x.example/{text}\"\n",
+        )]);
+        assert_eq!(issues(&report), ["actions[0] ('G'): 'search.google' is a built-in action"]);
+        assert_eq!(registry.get("search.google").unwrap().label(), "Google");
+        assert_eq!(report.loaded[0].action_count, 0);
+    }
+
+    #[test]
+    fn malformed_files_are_reported_whole() {
+        let (_, report) = load(&[
+            ("missing.toml", "[[actions]]\nname = \"X\"\nkind = \"url\"\n"),
+            ("output.toml", "[[actions]]\nname = \"X\"\nkind = \"shell\"\ntemplate = \"wc\"\noutput = \"print\"\n"),
+            ("unknown.json", r#"{"actions": [{"name": "X", "kind": "shell", "command": "wc", "template": "wc"}]}"#),
+            ("empty.toml", "name = \"Nothing\"\n"),
+        ]);
+        let messages = issues(&report);
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(messages[0].contains("no [[actions]]"), "{}", messages[0]);
+        assert!(messages[1].contains("missing field `template`"), "{}", messages[1]);
+        assert!(messages[2].contains("print"), "{}", messages[2]);
+        assert!(messages[3].contains("unknown field `command`"), "{}", messages[3]);
+        assert!(report.loaded.is_empty());
+    }
+
+    #[test]
+    fn invalid_actions_are_reported_and_skipped() {
+        let (_, report) = load(&[(
+            "p.toml",
+            r#"
+            [[actions]]
+            name = "Blank"
+            kind = "transform"
+            template = " "
+
+            [[actions]]
+            name = "Splice"
+            kind = "shell"
+            template = "echo {text}"
+
+            [[actions]]
+            name = "Other"
+            kind = "url"
+            template = "https://example.com/{query}"
+
+            [[actions]]
+            name = "Relative"
+            kind = "url"
+            template = "/browse/{text}"
+
+            [[actions]]
+            name = "Copied"
+            kind = "transform"
+            template = "{text}"
+            output = "copy"
+
+            [[actions]]
+            name = "Bad filter"
+            filter = "("
+            kind = "transform"
+            template = "{text}"
+            "#,
+        )]);
+        let messages = issues(&report);
+        assert_eq!(messages.len(), 6, "{:?}", messages);
+        assert!(messages[0].contains("`template` must not be empty"));
+        assert!(messages[1].contains("shell commands cannot use {text}"));
+        assert!(messages[2].contains("unknown placeholder '{query}'"));
+        assert!(messages[3].contains("must start with http"));
+        assert!(messages[4].contains("only apply to shell actions"));
+        assert!(messages[5].contains("invalid `filter` regex"));
+        assert_eq!(report.loaded[0].action_count, 0);
+    }
+
+    #[test]
+    fn placeholders_in_the_selection_are_not_substituted() {
+        assert_eq!(render_template("[{text}] ({text})", "a {text} b"), "[a {text} b] (a {text} b)");
+        assert_eq!(render_template("no placeholder", "x"), "no placeholder");
+        assert_eq!(render_template("{tex}{text}}", "x"), "{tex}x}");
+    }
+}
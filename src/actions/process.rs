@@ -0,0 +1,305 @@
+// Runs an external command with the selection on stdin.
+// Output is captured with a size cap, and the child is killed on timeout or cancellation.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::registry::CancelToken;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_MAX_OUTPUT: usize = 256 * 1024;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// How long the pipes may stay open after the command exits. Anything it wrote is in the
+// pipe by then; what keeps them open longer is a process it left running (`cmd &`, xdg-open).
+const DRAIN_GRACE: Duration = Duration::from_millis(200);
+// Larger selections are only on stdin: environment blocks are limited (32K characters in
+// total on Windows, 128 KB per variable on Linux) and can't hold NUL
+const MAX_ENV_TEXT: usize = 16 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ProcessSpec {
+    // Command line, run through the platform shell (`sh -c` / `cmd /C`)
+    pub command: String,
+    pub timeout: Duration,
+    pub max_output: usize,
+}
+
+impl ProcessSpec {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            timeout: DEFAULT_TIMEOUT,
+            max_output: DEFAULT_MAX_OUTPUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessOutput {
+    pub stdout: String,
+    pub stderr: String,
+    // stdout hit `max_output` and the rest was discarded
+    pub truncated: bool,
+}
+
+#[derive(Debug)]
+pub enum ProcessError {
+    Spawn(io::Error),
+    Io(io::Error),
+    Timeout(Duration),
+    Cancelled,
+    Failed { code: Option<i32>, stderr: String },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::Spawn(e) => write!(f, "failed to start command: {}", e),
+            ProcessError::Io(e) => write!(f, "I/O error: {}", e),
+            ProcessError::Timeout(t) => write!(f, "timed out after {:.1}s", t.as_secs_f32()),
+            ProcessError::Cancelled => write!(f, "cancelled"),
+            ProcessError::Failed { code, stderr } => {
+                match code {
+                    Some(code) => write!(f, "exited with code {}", code)?,
+                    None => write!(f, "terminated by signal")?,
+                }
+                let stderr = stderr.trim();
+                if !stderr.is_empty() {
+                    write!(f, ": {}", stderr)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn run(spec: &ProcessSpec, input: &str, cancel: &CancelToken) -> Result<ProcessOutput, ProcessError> {
+    let mut command = shell_command(&spec.command);
+    if input.len() <= MAX_ENV_TEXT && !input.contains('\0') {
+        command.env("POPWIN_TEXT", input);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ProcessError::Spawn)?;
+
+    // Feed stdin and drain stdout/stderr on their own threads so a chatty child can't deadlock us.
+    // None of them is joined: a grandchild (one that escaped the kill, or was started in the
+    // background) can hold the pipes open indefinitely, so they are left to finish on their own.
+    let stdin = child.stdin.take();
+    let input = input.as_bytes().to_vec();
+    thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            // A command that ignores stdin closes the pipe early; that's not an error
+            let _ = stdin.write_all(&input);
+        }
+    });
+    let stdout = child.stdout.take().map(|pipe| read_capped(pipe, spec.max_output));
+    let stderr = child.stderr.take().map(|pipe| read_capped(pipe, spec.max_output));
+
+    let status = wait_with_deadline(&mut child, spec.timeout, cancel)?;
+    let drained_by = Instant::now() + DRAIN_GRACE;
+    while [&stdout, &stderr].iter().any(|c| c.as_ref().is_some_and(|c| !c.lock().unwrap().done)) {
+        if Instant::now() >= drained_by {
+            log::debug!("`{}` left a process holding its output open; not waiting for it", spec.command);
+            break;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    let (stdout, truncated) = take_captured(stdout);
+    let (stderr, _) = take_captured(stderr);
+
+    if !status.success() {
+        return Err(ProcessError::Failed { code: status.code(), stderr });
+    }
+    Ok(ProcessOutput { stdout, stderr, truncated })
+}
+
+fn wait_with_deadline(
+    child: &mut Child,
+    timeout: Duration,
+    cancel: &CancelToken,
+) -> Result<std::process::ExitStatus, ProcessError> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().map_err(ProcessError::Io)? {
+            return Ok(status);
+        }
+        let error = if cancel.is_cancelled() {
+            ProcessError::Cancelled
+        } else if start.elapsed() >= timeout {
+            ProcessError::Timeout(timeout)
+        } else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        kill_tree(child);
+        let _ = child.wait();
+        return Err(error);
+    }
+}
+
+// What a reader thread has collected so far, so it can be taken without joining the thread
+#[derive(Default)]
+struct Captured {
+    kept: Vec<u8>,
+    truncated: bool,
+    // The pipe was closed
+    done: bool,
+}
+
+type Capture = Arc<Mutex<Captured>>;
+
+// Keep the first `limit` bytes, then keep draining so the child doesn't block on a full pipe
+fn read_capped(mut pipe: impl Read + Send + 'static, limit: usize) -> Capture {
+    let capture = Capture::default();
+    let shared = capture.clone();
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            match pipe.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    let mut captured = shared.lock().unwrap();
+                    let room = limit.saturating_sub(captured.kept.len());
+                    if n > room {
+                        captured.truncated = true;
+                    }
+                    captured.kept.extend_from_slice(&buf[..n.min(room)]);
+                }
+            }
+        }
+        shared.lock().unwrap().done = true;
+    });
+    capture
+}
+
+fn take_captured(capture: Option<Capture>) -> (String, bool) {
+    match capture {
+        Some(capture) => {
+            let mut captured = capture.lock().unwrap();
+            (String::from_utf8_lossy(&std::mem::take(&mut captured.kept)).into_owned(), captured.truncated)
+        }
+        None => (String::new(), false),
+    }
+}
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+#[cfg(target_os = "windows")]
+fn shell_command(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command).creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+#[cfg(target_os = "windows")]
+fn kill_tree(child: &mut Child) {
+    use std::os::windows::process::CommandExt;
+
+    // taskkill /T also takes down whatever cmd.exe started
+    let killed = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &child.id().to_string()])
+        .creation_flags(CREATE_NO_WINDOW)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !killed {
+        let _ = child.kill();
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell_command(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let mut cmd = Command::new("sh");
+    // Own process group, so kill_tree reaches the pipeline and not just sh
+    cmd.arg("-c").arg(command).process_group(0);
+    cmd
+}
+
+#[cfg(not(target_os = "windows"))]
+fn kill_tree(child: &mut Child) {
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn spec(command: &str, timeout_ms: u64) -> ProcessSpec {
+        ProcessSpec { timeout: Duration::from_millis(timeout_ms), ..ProcessSpec::new(command) }
+    }
+
+    #[test]
+    fn feeds_stdin_and_returns_stdout() {
+        let output = run(&spec("cat; echo \" $POPWIN_TEXT\"", 5000), "hello", &CancelToken::new()).unwrap();
+        assert_eq!(output.stdout, "hello hello\n");
+        assert!(!output.truncated);
+    }
+
+    #[test]
+    fn times_out() {
+        let started = Instant::now();
+        let error = run(&spec("sleep 5", 200), "", &CancelToken::new()).unwrap_err();
+        assert!(matches!(error, ProcessError::Timeout(_)), "{:?}", error);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let cancel = CancelToken::new();
+        let canceller = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        let started = Instant::now();
+        let error = run(&spec("sleep 5", 5000), "", &cancel).unwrap_err();
+        assert!(matches!(error, ProcessError::Cancelled), "{:?}", error);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn non_zero_exit_carries_the_code_and_stderr() {
+        match run(&spec("echo oops >&2; exit 3", 5000), "", &CancelToken::new()).unwrap_err() {
+            ProcessError::Failed { code, stderr } => {
+                assert_eq!(code, Some(3));
+                assert_eq!(stderr, "oops\n");
+            }
+            other => panic!("expected a failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn output_is_cut_at_max_output() {
+        let spec = ProcessSpec { max_output: 1000, ..spec("head -c 100000 /dev/zero | tr '\\0' x", 5000) };
+        let output = run(&spec, "", &CancelToken::new()).unwrap();
+        assert_eq!(output.stdout, "x".repeat(1000));
+        assert!(output.truncated);
+    }
+
+    #[test]
+    fn a_background_process_does_not_hold_up_the_result() {
+        let started = Instant::now();
+        let output = run(&spec("sleep 5 & echo hi", 10_000), "", &CancelToken::new()).unwrap();
+        assert_eq!(output.stdout, "hi\n");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,115 @@
+// Prompt-template actions that stream an LLM answer into the toolbar's result area
+
+use std::sync::Arc;
+
+use super::registry::{Action, ActionContext, ActionOutcome, ActionRegistry, PickerItem, Responder};
+use crate::config::{config_path, LoadIssue};
+use crate::llm::{builtin_prompts, LlmClient, LlmConfig, PromptTemplate};
+use crate::AppEvent;
+
+pub struct PromptAction {
+    id: String,
+    prompt: PromptTemplate,
+    client: Arc<LlmClient>,
+    pinned: bool,
+}
+
+impl Action for PromptAction {
+    fn id(&self) -> &str { &self.id }
+    fn label(&self) -> &str { &self.prompt.name }
+    fn icon(&self) -> &str { &self.prompt.icon }
+
+    fn show_in_toolbar(&self) -> bool {
+        self.pinned
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        let prompt = self.prompt.render(&ctx.selection.text);
+        stream_async(self.client.clone(), self.prompt.system.clone(), prompt, ctx.responder());
+        ActionOutcome::Pending(format!("{}...", self.prompt.name))
+    }
+}
+
+pub struct PromptPickerAction {
+    items: Vec<PickerItem>,
+}
+
+impl Action for PromptPickerAction {
+    fn id(&self) -> &str { "llm.picker" }
+    fn label(&self) -> &str { "AI…" }
+    fn icon(&self) -> &str { "✨" }
+
+    fn run(&self, _ctx: &ActionContext) -> ActionOutcome {
+        ActionOutcome::Pick(self.items.clone())
+    }
+}
+
+fn stream_async(client: Arc<LlmClient>, system: Option<String>, prompt: String, responder: Responder) {
+    std::thread::spawn(move || {
+        let request_id = responder.request_id;
+        let result = client.stream(system.as_deref(), &prompt, &responder.cancel, |delta| {
+            responder.send(AppEvent::StreamChunk { request_id, text: delta.to_string() });
+        });
+        match result {
+            Ok(true) => responder.send(AppEvent::StreamFinished { request_id, result: Ok(()) }),
+            Ok(false) => log::info!("LLM request {} cancelled", request_id),
+            Err(e) => {
+                log::warn!("LLM request {} failed: {}", request_id, e);
+                responder.send(AppEvent::StreamFinished { request_id, result: Err(e) });
+            }
+        }
+    });
+}
+
+// Nothing is registered until `llm.model` is set, so an unconfigured install shows no dead buttons
+pub fn register_prompts(registry: &mut ActionRegistry, config: &LlmConfig, issues: &mut Vec<LoadIssue>) {
+    let Some(model) = config.model.as_deref() else {
+        return;
+    };
+    let client = Arc::new(LlmClient::new(config, model));
+
+    let mut prompts = builtin_prompts();
+    for prompt in &config.prompts {
+        if prompt.id.trim().is_empty() || prompt.name.trim().is_empty() || prompt.template.trim().is_empty() {
+            issues.push(LoadIssue::new(
+                config_path(),
+                format!("llm prompt '{}': `id`, `name` and `template` must not be empty", prompt.id),
+            ));
+            continue;
+        }
+        match prompts.iter_mut().find(|p| p.id == prompt.id) {
+            Some(existing) => *existing = prompt.clone(),
+            None => prompts.push(prompt.clone()),
+        }
+    }
+    for id in &config.toolbar {
+        if !prompts.iter().any(|p| &p.id == id) {
+            issues.push(LoadIssue::new(config_path(), format!("llm.toolbar: unknown prompt '{}'", id)));
+        }
+    }
+
+    let items = prompts
+        .iter()
+        .filter(|p| !config.toolbar.contains(&p.id))
+        .map(|p| PickerItem {
+            action_id: format!("llm.{}", p.id),
+            option: None,
+            label: format!("{} {}", p.icon, p.name).trim().to_string(),
+            keyword: p.id.clone(),
+            detail: String::new(),
+            search_text: String::new(),
+        })
+        .collect::<Vec<_>>();
+
+    for prompt in prompts {
+        registry.register(Box::new(PromptAction {
+            id: format!("llm.{}", prompt.id),
+            pinned: config.toolbar.contains(&prompt.id),
+            prompt,
+            client: client.clone(),
+        }));
+    }
+    if !items.is_empty() {
+        registry.register(Box::new(PromptPickerAction { items }));
+    }
+}
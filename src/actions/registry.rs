@@ -0,0 +1,233 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crossbeam_channel::Sender;
+
+use crate::automation::AppInfo;
+use crate::config::{Config, LoadIssue};
+use crate::AppEvent;
+
+// Snapshot of what the user selected, handed to every action
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    pub text: String,
+    pub position: (i32, i32),
+    pub app: AppInfo,
+}
+
+impl Selection {
+    pub fn new(text: impl Into<String>, position: (i32, i32)) -> Self {
+        Self { text: text.into(), position, app: AppInfo::default() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+}
+
+// Shared flag that background work polls; the toolbar cancels it when the selection goes away
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Tags every async reply so the toolbar can drop replies for a selection it no longer shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestId(u64);
+
+impl RequestId {
+    pub fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        RequestId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// Handed to background work so it can report back for one request
+#[derive(Debug, Clone)]
+pub struct Responder {
+    pub request_id: RequestId,
+    pub cancel: CancelToken,
+    sender: Sender<AppEvent>,
+}
+
+impl Responder {
+    pub fn new(request_id: RequestId, cancel: CancelToken, sender: Sender<AppEvent>) -> Self {
+        Self { request_id, cancel, sender }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    // Replies for a cancelled request are dropped here rather than racing the UI
+    pub fn send(&self, event: AppEvent) {
+        if self.is_cancelled() {
+            log::debug!("Dropping reply for cancelled request {}", self.request_id);
+            return;
+        }
+        let _ = self.sender.send(event);
+    }
+}
+
+pub struct ActionContext<'a> {
+    pub selection: &'a Selection,
+    pub sender: &'a Sender<AppEvent>,
+    pub cancel: &'a CancelToken,
+    pub request_id: RequestId,
+    // Id of the ActionOption the user picked from the button's menu, if any
+    pub option: Option<&'a str>,
+    // Shift+click: don't serve cached results
+    pub force_refresh: bool,
+}
+
+impl ActionContext<'_> {
+    pub fn responder(&self) -> Responder {
+        Responder::new(self.request_id, self.cancel.clone(), self.sender.clone())
+    }
+}
+
+// Per-invocation variant of an action, offered in a small menu next to its button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionOption {
+    pub id: String,
+    pub label: String,
+}
+
+// Where the output of a background action goes once it arrives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    // Show it in the toolbar's result area
+    #[default]
+    Show,
+    // Put it on the clipboard
+    Copy,
+    // Paste it over the current selection
+    Replace,
+}
+
+// One entry of a picker; choosing it runs the action with `action_id` and `option`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerItem {
+    pub action_id: String,
+    pub option: Option<String>,
+    pub label: String,
+    // Short keyword matched by prefix, e.g. "gh" for GitHub
+    pub keyword: String,
+    // Dimmed text next to the label (not searched)
+    pub detail: String,
+    // Searched like the label but not shown, e.g. the full text behind a shortened label
+    pub search_text: String,
+}
+
+impl PickerItem {
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.keyword.to_lowercase().starts_with(&query)
+            || self.label.to_lowercase().contains(&query)
+            || self.search_text.to_lowercase().contains(&query)
+    }
+}
+
+// What the toolbar should do after an action ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionOutcome {
+    // Action is done, hide the toolbar
+    Dismiss,
+    // Action continues in the background; keep the toolbar open with a status message
+    Pending(String),
+    // Let the user choose among more actions than fit on the toolbar
+    Pick(Vec<PickerItem>),
+}
+
+pub trait Action: Send + Sync {
+    fn id(&self) -> &str;
+    fn label(&self) -> &str;
+    fn icon(&self) -> &str;
+
+    fn is_available(&self, selection: &Selection) -> bool {
+        !selection.is_empty()
+    }
+
+    // Actions that return false are only reachable through a picker
+    fn show_in_toolbar(&self) -> bool {
+        true
+    }
+
+    fn options(&self) -> Vec<ActionOption> {
+        Vec::new()
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome;
+
+    fn button_text(&self) -> String {
+        if self.icon().is_empty() {
+            self.label().to_string()
+        } else {
+            format!("{} {}", self.icon(), self.label())
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ActionRegistry {
+    actions: Vec<Box<dyn Action>>,
+}
+
+impl ActionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Built-in actions plus configured search engines; config problems are returned, not fatal
+    pub fn with_builtins(config: &Config) -> (Self, Vec<LoadIssue>) {
+        let mut registry = Self::new();
+        let issues = super::builtin::register_builtins(&mut registry, config);
+        (registry, issues)
+    }
+
+    // Registering an id twice replaces the earlier action but keeps its toolbar slot
+    pub fn register(&mut self, action: Box<dyn Action>) {
+        if let Some(slot) = self.actions.iter_mut().find(|a| a.id() == action.id()) {
+            log::warn!("Action '{}' registered twice, replacing", action.id());
+            *slot = action;
+        } else {
+            self.actions.push(action);
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn Action> {
+        self.actions.iter().find(|a| a.id() == id).map(|a| a.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Action> {
+        self.actions.iter().map(|a| a.as_ref())
+    }
+
+    // Buttons to show for this selection, as far as the app's [[apps]] rule allows
+    pub fn toolbar<'a>(&'a self, selection: &'a Selection) -> impl Iterator<Item = &'a dyn Action> + 'a {
+        let profile = crate::profiles::profile_for(&selection.app);
+        self.iter()
+            .filter(move |a| a.show_in_toolbar() && a.is_available(selection) && profile.allows(a.id()))
+    }
+}
@@ -0,0 +1,241 @@
+// URL-template search engines. Each engine is registered as its own action; the first
+// few get toolbar buttons and the rest are reachable through the keyword picker.
+//
+//   [[search.engines]]
+//   id = "wiki"
+//   name = "Team wiki"
+//   keyword = "w"
+//   template = "https://wiki.example.com/search?q={query}"
+//   encoding = "form"      # form | percent (default) | path
+//   prompt_prefix = ""     # prepended to the selection before encoding
+//
+// `{query}` is the encoded query, `{query_raw}` is inserted as-is.
+
+use serde::Deserialize;
+
+use super::registry::{Action, ActionContext, ActionOutcome, ActionRegistry, PickerItem};
+use crate::config::{config_path, LoadIssue, SearchConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryEncoding {
+    // application/x-www-form-urlencoded: spaces become '+' (query strings of most search pages)
+    Form,
+    // RFC 3986 component encoding: spaces become %20, '/' is escaped
+    #[default]
+    Percent,
+    // Like percent, but '/' is kept so the query can span path segments (docs.rs/serde/latest)
+    Path,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchEngine {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub icon: String,
+    #[serde(default)]
+    pub keyword: String,
+    pub template: String,
+    #[serde(default)]
+    pub encoding: QueryEncoding,
+    #[serde(default)]
+    pub prompt_prefix: String,
+}
+
+impl SearchEngine {
+    fn builtin(id: &str, name: &str, icon: &str, keyword: &str, template: &str, encoding: QueryEncoding) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            icon: icon.to_string(),
+            keyword: keyword.to_string(),
+            template: template.to_string(),
+            encoding,
+            prompt_prefix: String::new(),
+        }
+    }
+
+    // One pass over the template, so a selection containing "{query}" is never substituted again
+    pub fn url_for(&self, text: &str) -> String {
+        let query = format!("{}{}", self.prompt_prefix, text);
+        let mut url = String::with_capacity(self.template.len() + query.len());
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            url.push_str(&rest[..start]);
+            rest = &rest[start..];
+            if let Some(after) = rest.strip_prefix("{query_raw}") {
+                url.push_str(&query);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("{query}") {
+                url.push_str(&encode(&query, self.encoding));
+                rest = after;
+            } else {
+                url.push('{');
+                rest = &rest[1..];
+            }
+        }
+        url.push_str(rest);
+        url
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() || self.name.trim().is_empty() {
+            return Err("`id` and `name` must not be empty".to_string());
+        }
+        if !(self.template.starts_with("https://") || self.template.starts_with("http://")) {
+            return Err("`template` must start with http:// or https://".to_string());
+        }
+        if !self.template.contains("{query}") && !self.template.contains("{query_raw}") {
+            return Err("`template` must contain {query} or {query_raw}".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn builtin_engines() -> Vec<SearchEngine> {
+    use QueryEncoding::*;
+    vec![
+        SearchEngine::builtin("perplexity", "Perplexity", "🔍", "p", "https://www.perplexity.ai/search?q={query}", Percent),
+        SearchEngine::builtin("google", "Google", "G", "g", "https://www.google.com/search?q={query}", Form),
+        SearchEngine::builtin("duckduckgo", "DuckDuckGo", "🦆", "d", "https://duckduckgo.com/?q={query}", Form),
+        SearchEngine::builtin("wikipedia", "Wikipedia", "W", "w", "https://en.wikipedia.org/w/index.php?search={query}", Form),
+        SearchEngine::builtin("github", "GitHub code", "🐙", "gh", "https://github.com/search?type=code&q={query}", Form),
+        SearchEngine::builtin("mdn", "MDN", "📘", "mdn", "https://developer.mozilla.org/en-US/search?q={query}", Form),
+        SearchEngine::builtin("docsrs", "docs.rs", "🦀", "rs", "https://docs.rs/releases/search?query={query}", Form),
+    ]
+}
+
+pub struct SearchAction {
+    id: String,
+    engine: SearchEngine,
+    pinned: bool,
+}
+
+impl Action for SearchAction {
+    fn id(&self) -> &str { &self.id }
+    fn label(&self) -> &str { &self.engine.name }
+    fn icon(&self) -> &str { &self.engine.icon }
+
+    fn show_in_toolbar(&self) -> bool {
+        self.pinned
+    }
+
+    fn run(&self, ctx: &ActionContext) -> ActionOutcome {
+        let url = self.engine.url_for(&ctx.selection.text);
+        if let Err(e) = webbrowser::open(&url) {
+            log::error!("Failed to open browser: {}", e);
+        }
+        ActionOutcome::Dismiss
+    }
+}
+
+// Toolbar entry that lists every engine, filtered by keyword
+pub struct SearchPickerAction {
+    items: Vec<PickerItem>,
+}
+
+impl Action for SearchPickerAction {
+    fn id(&self) -> &str { "search.picker" }
+    fn label(&self) -> &str { "Search…" }
+    fn icon(&self) -> &str { "🔎" }
+
+    fn run(&self, _ctx: &ActionContext) -> ActionOutcome {
+        ActionOutcome::Pick(self.items.clone())
+    }
+}
+
+// Register built-in and configured engines. Configured engines replace built-ins with the same id.
+pub fn register_search_engines(registry: &mut ActionRegistry, config: &SearchConfig, issues: &mut Vec<LoadIssue>) {
+    let mut engines = builtin_engines();
+    for engine in &config.engines {
+        if let Err(message) = engine.validate() {
+            issues.push(LoadIssue::new(config_path(), format!("search engine '{}': {}", engine.id, message)));
+            continue;
+        }
+        match engines.iter_mut().find(|e| e.id == engine.id) {
+            Some(existing) => *existing = engine.clone(),
+            None => engines.push(engine.clone()),
+        }
+    }
+
+    // Buttons: the ids listed in `toolbar`, otherwise the first `max_buttons` engines
+    let pinned: Vec<String> = if config.toolbar.is_empty() {
+        engines.iter().take(config.max_buttons).map(|e| e.id.clone()).collect()
+    } else {
+        config.toolbar.clone()
+    };
+    for id in &pinned {
+        if !engines.iter().any(|e| &e.id == id) {
+            issues.push(LoadIssue::new(config_path(), format!("search.toolbar: unknown engine '{}'", id)));
+        }
+    }
+
+    let items = engines
+        .iter()
+        .map(|e| PickerItem {
+            action_id: format!("search.{}", e.id),
+            option: None,
+            label: format!("{} {}", e.icon, e.name).trim().to_string(),
+            keyword: e.keyword.clone(),
+            detail: String::new(),
+            search_text: String::new(),
+        })
+        .collect::<Vec<_>>();
+    let needs_picker = engines.iter().any(|e| !pinned.contains(&e.id));
+
+    for engine in engines {
+        registry.register(Box::new(SearchAction {
+            id: format!("search.{}", engine.id),
+            pinned: pinned.contains(&engine.id),
+            engine,
+        }));
+    }
+    if needs_picker {
+        registry.register(Box::new(SearchPickerAction { items }));
+    }
+}
+
+fn encode(query: &str, encoding: QueryEncoding) -> String {
+    match encoding {
+        QueryEncoding::Percent => urlencoding::encode(query).into_owned(),
+        QueryEncoding::Path => query
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect::<Vec<_>>()
+            .join("/"),
+        QueryEncoding::Form => {
+            let mut out = String::with_capacity(query.len());
+            for byte in query.bytes() {
+                match byte {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'*' => out.push(byte as char),
+                    b' ' => out.push('+'),
+                    _ => out.push_str(&format!("%{:02X}", byte)),
+                }
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_in_the_selection_are_not_substituted() {
+        let engine = SearchEngine::builtin(
+            "t",
+            "Test",
+            "",
+            "t",
+            "https://example.com/{query_raw}?q={query}&keep={other}",
+            QueryEncoding::Form,
+        );
+        assert_eq!(
+            engine.url_for("a {query} b"),
+            "https://example.com/a {query} b?q=a+%7Bquery%7D+b&keep={other}"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+// Shortcuts on X11 through the XTEST extension, as if typed on the keyboard.
+// Keycodes come from the server's keyboard mapping, so non-US layouts work as long as the
+// layout has Latin c / v / x somewhere.
+
+use std::error::Error;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt as _, Keycode, Window, KEY_PRESS_EVENT, KEY_RELEASE_EVENT};
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+
+use crate::platform::{InputInjector, Shortcut};
+
+const XK_CONTROL_L: u32 = 0xffe3;
+const XK_C: u32 = 0x0063;
+const XK_V: u32 = 0x0076;
+const XK_X: u32 = 0x0078;
+
+pub struct XTestInjector {
+    conn: RustConnection,
+    root: Window,
+    control: Keycode,
+    copy: Keycode,
+    cut: Keycode,
+    paste: Keycode,
+}
+
+impl XTestInjector {
+    pub fn connect() -> Result<Self, Box<dyn Error>> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        conn.xtest_get_version(2, 2)?.reply()?;
+
+        let (min, max) = (conn.setup().min_keycode, conn.setup().max_keycode);
+        let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+        let per_keycode = mapping.keysyms_per_keycode as usize;
+        if per_keycode == 0 {
+            return Err("empty keyboard mapping".into());
+        }
+        let keycode = |keysym: u32| -> Result<Keycode, Box<dyn Error>> {
+            mapping
+                .keysyms
+                .chunks(per_keycode)
+                .position(|keysyms| keysyms.contains(&keysym))
+                .map(|index| min + index as u8)
+                .ok_or_else(|| format!("no key produces keysym {:#x}", keysym).into())
+        };
+        Ok(Self {
+            control: keycode(XK_CONTROL_L)?,
+            copy: keycode(XK_C)?,
+            cut: keycode(XK_X)?,
+            paste: keycode(XK_V)?,
+            conn,
+            root,
+        })
+    }
+
+    fn send_combo(&self, key: Keycode) -> Result<(), Box<dyn Error>> {
+        for (kind, keycode) in [
+            (KEY_PRESS_EVENT, self.control),
+            (KEY_PRESS_EVENT, key),
+            (KEY_RELEASE_EVENT, key),
+            (KEY_RELEASE_EVENT, self.control),
+        ] {
+            self.conn.xtest_fake_input(kind, keycode, x11rb::CURRENT_TIME, self.root, 0, 0, 0)?;
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+impl InputInjector for XTestInjector {
+    fn send(&self, shortcut: Shortcut) {
+        let key = match shortcut {
+            Shortcut::Copy => self.copy,
+            Shortcut::Cut => self.cut,
+            Shortcut::Paste => self.paste,
+        };
+        if let Err(e) = self.send_combo(key) {
+            log::warn!("Cannot send {:?} through XTEST: {}", shortcut, e);
+        }
+    }
+}
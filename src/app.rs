@@ -1,52 +1,399 @@
+use std::collections::HashSet;
+
 use eframe::{egui, App, CreationContext, Frame};
 use crossbeam_channel::{Receiver, Sender};
 
+use crate::translation::{RoundTrip, Translation, TranslationError, TranslationResult};
 use crate::AppEvent;
-use crate::actions;
+use crate::actions::{
+    self, ActionContext, ActionOutcome, ActionRegistry, CancelToken, OutputMode, PackReport, PickerItem, RequestId,
+    Selection,
+};
+
+// What the result area under the buttons currently shows
+enum ResultView {
+    Status(String),
+    Translation(Translation),
+    Output(String),
+    Error(String),
+    TranslationFailed(TranslationError),
+    // Streaming LLM answer; `error` is set if the stream broke off
+    Stream { text: String, done: bool, error: Option<String> },
+    RoundTrip(RoundTrip),
+    // One section per provider; `None` until that provider answers
+    Comparison(Vec<(String, Option<TranslationResult>)>),
+}
+
+impl ResultView {
+    // Text the Copy / Replace buttons act on
+    fn copyable_text(&self) -> Option<&str> {
+        match self {
+            ResultView::Translation(translation) => Some(&translation.text),
+            ResultView::RoundTrip(round_trip) => Some(&round_trip.translation.text),
+            ResultView::Output(text) => Some(text),
+            ResultView::Stream { text, .. } if !text.is_empty() => Some(text),
+            _ => None,
+        }
+    }
+}
+
+enum ResultCommand {
+    Retry,
+    Copy(String),
+    Replace(String),
+}
+
+// Keyword picker for actions that don't fit on the toolbar
+struct Picker {
+    items: Vec<PickerItem>,
+    query: String,
+}
+
+impl Picker {
+    // Exact keyword hits first, so "g" picks Google even though "gh" also matches
+    fn matches(&self) -> Vec<&PickerItem> {
+        let query = self.query.trim();
+        let mut matches: Vec<&PickerItem> = self.items.iter().filter(|i| i.matches(query)).collect();
+        matches.sort_by_key(|i| !i.keyword.eq_ignore_ascii_case(query));
+        matches
+    }
+}
 
 pub struct PopWinApp {
     visible: bool,
-    position: (i32, i32),
-    selected_text: String,
-    translation: Option<String>,
+    selection: Selection,
+    // Cancelled whenever the selection changes or the toolbar hides
+    cancel: CancelToken,
+    // Async requests started for the current selection; replies for anything else are stale
+    pending: HashSet<RequestId>,
+    result: Option<ResultView>,
+    picker: Option<Picker>,
+    // Action id and option of the last click, for Retry
+    last_invocation: Option<(String, Option<String>)>,
+    registry: ActionRegistry,
+    pack_report: PackReport,
+    show_settings: bool,
+    // Whether clicks currently pass through the (hidden) toolbar window
+    click_through: Option<bool>,
     event_receiver: Receiver<AppEvent>,
     event_sender: Sender<AppEvent>,
 }
 
 impl PopWinApp {
-    pub fn new(_cc: &CreationContext, receiver: Receiver<AppEvent>, sender: Sender<AppEvent>) -> Self {
+    pub fn new(
+        _cc: &CreationContext,
+        registry: ActionRegistry,
+        pack_report: PackReport,
+        receiver: Receiver<AppEvent>,
+        sender: Sender<AppEvent>,
+    ) -> Self {
         // Customize fonts or style here if needed
         Self {
             visible: false, // Initially hidden
-            position: (0, 0),
-            selected_text: String::new(),
-            translation: None,
+            selection: Selection::default(),
+            cancel: CancelToken::new(),
+            pending: HashSet::new(),
+            result: None,
+            picker: None,
+            last_invocation: None,
+            registry,
+            pack_report,
+            show_settings: false,
+            click_through: None,
             event_receiver: receiver,
             event_sender: sender,
         }
     }
+
+    // Loaded action packs and any config/pack problems found at startup
+    fn settings_ui(&self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("Action packs").strong());
+        if self.pack_report.loaded.is_empty() {
+            ui.label("(none)");
+        }
+        for pack in &self.pack_report.loaded {
+            ui.label(format!("{} ({} actions)", pack.name, pack.action_count))
+                .on_hover_text(pack.path.display().to_string());
+        }
+        if !self.pack_report.issues.is_empty() {
+            ui.label(egui::RichText::new("Problems").strong().color(egui::Color32::YELLOW));
+            for issue in &self.pack_report.issues {
+                ui.add(egui::Label::new(egui::RichText::new(issue.to_string()).color(egui::Color32::YELLOW)).wrap(true));
+            }
+        }
+    }
+
+    fn run_action(&mut self, id: &str, option: Option<&str>, force_refresh: bool) {
+        let Some(action) = self.registry.get(id) else {
+            return;
+        };
+        // Picker entries run actions that have no button; the app's [[apps]] rule applies to them too
+        if !crate::profiles::profile_for(&self.selection.app).allows(id) {
+            log::info!("Action '{}' is turned off for {:?}", id, self.selection.app);
+            return;
+        }
+        self.last_invocation = Some((id.to_string(), option.map(|o| o.to_string())));
+        let request_id = RequestId::next();
+        let ctx = ActionContext {
+            selection: &self.selection,
+            sender: &self.event_sender,
+            cancel: &self.cancel,
+            request_id,
+            option,
+            force_refresh,
+        };
+        match action.run(&ctx) {
+            ActionOutcome::Dismiss => self.hide(),
+            ActionOutcome::Pending(status) => {
+                self.pending.insert(request_id);
+                self.result = Some(ResultView::Status(status));
+            }
+            ActionOutcome::Pick(items) => self.picker = Some(Picker { items, query: String::new() }),
+        }
+    }
+
+    // Romanization, dictionary entries and alternatives under the main translation
+    fn translation_details_ui(translation: &Translation, ui: &mut egui::Ui) {
+        if !translation.has_details() {
+            return;
+        }
+        let dim = |text: String| egui::RichText::new(text).small().color(egui::Color32::GRAY);
+        if let Some(romanization) = &translation.romanization {
+            ui.label(dim(romanization.clone()).italics());
+        }
+        if let Some(romanization) = &translation.source_romanization {
+            ui.label(dim(format!("({})", romanization)).italics());
+        }
+        for entry in &translation.dictionary {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(egui::RichText::new(&entry.part_of_speech).small().strong());
+                ui.label(egui::RichText::new(entry.terms.join(", ")).small());
+            });
+        }
+        if !translation.alternatives.is_empty() {
+            ui.label(dim(format!("他の訳: {}", translation.alternatives.join(" / "))));
+        }
+        if let Some(lang) = &translation.detected_source {
+            ui.label(dim(format!("検出言語: {}", lang.to_uppercase())));
+        }
+    }
+
+    fn round_trip_ui(round_trip: &RoundTrip, ui: &mut egui::Ui) {
+        let heading = |ui: &mut egui::Ui, text: &str| {
+            ui.label(egui::RichText::new(text).small().color(egui::Color32::GRAY));
+        };
+        heading(ui, "原文");
+        ui.add(egui::Label::new(&round_trip.original).wrap(true));
+        heading(ui, "翻訳");
+        ui.add(egui::Label::new(translation_text(&round_trip.translation)).wrap(true));
+        heading(ui, "逆翻訳");
+        ui.add(egui::Label::new(egui::RichText::new(&round_trip.back.text).color(egui::Color32::LIGHT_GRAY)).wrap(true));
+    }
+
+    fn comparison_section_ui(provider: &str, slot: &Option<TranslationResult>, ui: &mut egui::Ui) -> Option<ResultCommand> {
+        let mut command = None;
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(provider).strong());
+            if let Some(Ok(translation)) = slot {
+                if ui.small_button("📋").on_hover_text("Copy").clicked() {
+                    command = Some(ResultCommand::Copy(translation.text.clone()));
+                }
+                if ui.small_button("⇄").on_hover_text("Paste over the selection").clicked() {
+                    command = Some(ResultCommand::Replace(translation.text.clone()));
+                }
+            }
+        });
+        match slot {
+            None => {
+                ui.label(egui::RichText::new("翻訳中...").color(egui::Color32::GRAY));
+            }
+            Some(Ok(translation)) => {
+                ui.add(egui::Label::new(translation_text(translation)).wrap(true));
+            }
+            Some(Err(error)) => {
+                ui.label(egui::RichText::new(format!("⚠ {}", error.summary())).color(egui::Color32::LIGHT_RED))
+                    .on_hover_text(error.to_string());
+            }
+        }
+        command
+    }
+
+    // Returns the id of the chosen action, if any
+    fn picker_ui(picker: &mut Picker, ui: &mut egui::Ui) -> Option<(String, Option<String>)> {
+        let response = ui.add(egui::TextEdit::singleline(&mut picker.query).hint_text("keyword…"));
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        response.request_focus();
+
+        let matches = picker.matches();
+        let mut chosen = None;
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            for item in &matches {
+                ui.horizontal(|ui| {
+                    if ui.button(&item.label).clicked() {
+                        chosen = Some((item.action_id.clone(), item.option.clone()));
+                    }
+                    if !item.keyword.is_empty() {
+                        ui.weak(&item.keyword);
+                    }
+                    if !item.detail.is_empty() {
+                        ui.label(egui::RichText::new(&item.detail).small().weak());
+                    }
+                });
+            }
+        });
+        if submitted {
+            chosen = matches.first().map(|i| (i.action_id.clone(), i.option.clone()));
+        }
+        chosen
+    }
+
+    // Cancel everything started for the previous selection
+    fn reset_session(&mut self) {
+        self.cancel.cancel();
+        self.cancel = CancelToken::new();
+        self.pending.clear();
+        self.result = None;
+        self.picker = None;
+    }
+
+    fn hide(&mut self) {
+        self.reset_session();
+        self.visible = false;
+    }
+
+    // Accept a reply only once, and only for the selection it was started for
+    fn take_pending(&mut self, request_id: RequestId) -> bool {
+        if self.pending.remove(&request_id) {
+            true
+        } else {
+            log::debug!("Ignoring stale reply for request {}", request_id);
+            false
+        }
+    }
+
+    fn handle_action_output(&mut self, action_id: &str, mode: OutputMode, result: Result<String, String>) {
+        match (result, mode) {
+            (Err(message), _) => {
+                let label = self.registry.get(action_id).map(|a| a.label().to_string()).unwrap_or_default();
+                self.result = Some(ResultView::Error(format!("{}: {}", label, message)));
+            }
+            (Ok(text), OutputMode::Show) => {
+                let text = if text.trim().is_empty() { "(no output)".to_string() } else { text };
+                self.result = Some(ResultView::Output(text));
+            }
+            (Ok(text), OutputMode::Copy) => {
+                actions::copy_selection(&text, &self.selection.app);
+                self.hide();
+            }
+            (Ok(text), OutputMode::Replace) => {
+                actions::copy_selection(&text, &self.selection.app);
+                actions::paste();
+                self.hide();
+            }
+        }
+    }
 }
 
 impl App for PopWinApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        // Nothing wakes egui when another thread sends an event, so keep polling while idle
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+
         // Poll for events from the background thread
         while let Ok(event) = self.event_receiver.try_recv() {
             match event {
-                AppEvent::SelectionDetected { text, position } => {
-                    self.selected_text = text;
-                    self.position = position;
+                AppEvent::SelectionDetected { text, position, app } => {
+                    self.reset_session();
+                    self.selection = Selection { app, ..Selection::new(text, position) };
                     self.visible = true;
-                    self.translation = None; // Reset translation
                     ctx.request_repaint();
                 }
                 AppEvent::SelectionCleared => {
-                    self.visible = false;
-                    self.translation = None;
+                    // The global hook also sees presses on the toolbar itself
+                    if self.visible && ctx.input(|i| i.pointer.hover_pos().is_some()) {
+                        continue;
+                    }
+                    self.hide();
                     ctx.request_repaint();
                 }
-                AppEvent::TranslationReceived(text) => {
-                    self.translation = Some(text);
-                    ctx.request_repaint();
+                AppEvent::TranslationReceived { request_id, translation } => {
+                    if self.take_pending(request_id) {
+                        self.result = Some(ResultView::Translation(translation));
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::RoundTripReceived { request_id, round_trip } => {
+                    if self.take_pending(request_id) {
+                        self.result = Some(ResultView::RoundTrip(round_trip));
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::TranslationFailed { request_id, error } => {
+                    if self.take_pending(request_id) {
+                        self.result = Some(ResultView::TranslationFailed(error));
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::TranslationProgress { request_id, done, total } => {
+                    if self.pending.contains(&request_id) {
+                        self.result = Some(ResultView::Status(format!("翻訳中... {}/{}", done, total)));
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::ComparisonStarted { request_id, providers } => {
+                    if self.pending.contains(&request_id) {
+                        self.result = Some(ResultView::Comparison(providers.into_iter().map(|p| (p, None)).collect()));
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::ComparisonResult { request_id, provider, result } => {
+                    if !self.pending.contains(&request_id) {
+                        continue;
+                    }
+                    if let Some(ResultView::Comparison(sections)) = &mut self.result {
+                        if let Some((_, slot)) = sections.iter_mut().find(|(p, _)| *p == provider) {
+                            *slot = Some(result);
+                        }
+                        if sections.iter().all(|(_, slot)| slot.is_some()) {
+                            self.pending.remove(&request_id);
+                        }
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::StreamChunk { request_id, text } => {
+                    if self.pending.contains(&request_id) {
+                        match &mut self.result {
+                            Some(ResultView::Stream { text: buffer, .. }) => buffer.push_str(&text),
+                            _ => self.result = Some(ResultView::Stream { text, done: false, error: None }),
+                        }
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::StreamFinished { request_id, result } => {
+                    if self.take_pending(request_id) {
+                        let error = result.err();
+                        match &mut self.result {
+                            Some(ResultView::Stream { done, error: slot, .. }) => {
+                                *done = true;
+                                *slot = error;
+                            }
+                            // Failed before the first token
+                            _ => {
+                                self.result = Some(match error {
+                                    Some(error) => ResultView::Error(error),
+                                    None => ResultView::Stream { text: String::new(), done: true, error: None },
+                                });
+                            }
+                        }
+                        ctx.request_repaint();
+                    }
+                }
+                AppEvent::ActionOutput { request_id, action_id, mode, result } => {
+                    if self.take_pending(request_id) {
+                        self.handle_action_output(&action_id, mode, result);
+                        ctx.request_repaint();
+                    }
                 }
             }
         }
@@ -58,14 +405,20 @@ impl App for PopWinApp {
             0.15,
         );
 
-        if alpha == 0.0 {
+        // The window never really closes; while hidden, clicks must reach whatever is under it
+        let hidden = alpha == 0.0;
+        if self.click_through != Some(hidden) {
+            self.click_through = Some(hidden);
+            ctx.send_viewport_cmd(egui::ViewportCommand::MousePassthrough(hidden));
+        }
+        if hidden {
             return;
         }
 
         // Slide up from bottom: offset decreases from 10px to 0px as alpha goes 0→1
         let slide_offset = (1.0 - alpha) * 10.0;
-        let x = (self.position.0 - 90) as f32;
-        let y = self.position.1 as f32 + slide_offset;
+        let x = (self.selection.position.0 - 90) as f32;
+        let y = self.selection.position.1 as f32 + slide_offset;
         ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::Pos2::new(x, y)));
 
         // Apply custom window styling with animated opacity
@@ -80,45 +433,148 @@ impl App for PopWinApp {
             ui.vertical(|ui| {
                 ui.style_mut().spacing.item_spacing = egui::vec2(5.0, 5.0);
 
-                // Row 1: Clipboard Actions
-                ui.horizontal(|ui| {
-                    if ui.button("📋 Copy").clicked() {
-                        actions::copy_selection(&self.selected_text);
-                        self.visible = false;
-                    }
-                    if ui.button("✂️ Cut").clicked() {
-                        actions::cut();
-                        self.visible = false;
-                    }
-                     if ui.button("📄 Paste").clicked() {
-                        actions::paste();
-                        self.visible = false;
+                // Toolbar buttons, built from the action registry
+                let mut clicked: Option<(String, Option<String>)> = None;
+                ui.horizontal_wrapped(|ui| {
+                    for action in self.registry.toolbar(&self.selection) {
+                        if ui.button(action.button_text()).clicked() {
+                            clicked = Some((action.id().to_string(), None));
+                        }
+                        let options = action.options();
+                        if !options.is_empty() {
+                            ui.menu_button("▾", |ui| {
+                                for option in options {
+                                    if ui.button(&option.label).clicked() {
+                                        clicked = Some((action.id().to_string(), Some(option.id)));
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                        }
                     }
                 });
+                if let Some(picker) = &mut self.picker {
+                    ui.separator();
+                    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        self.picker = None;
+                    } else if let Some(choice) = Self::picker_ui(picker, ui) {
+                        self.picker = None;
+                        clicked = Some(choice);
+                    }
+                }
+                if let Some((id, option)) = clicked {
+                    let force_refresh = ui.input(|i| i.modifiers.shift);
+                    self.run_action(&id, option.as_deref(), force_refresh);
+                }
 
-                // Row 2: Search & Translate
-                ui.horizontal(|ui| {
-                    if ui.button("🔍 Perplexity").clicked() {
-                        actions::search_perplexity(&self.selected_text);
-                        self.visible = false;
+                // Result Area (translation, command output, errors)
+                let mut command = None;
+                if let Some(result) = &self.result {
+                    ui.separator();
+                    let text: egui::WidgetText = match result {
+                        ResultView::Status(text) => egui::RichText::new(text).color(egui::Color32::GRAY).into(),
+                        ResultView::Translation(translation) => translation_text(translation),
+                        ResultView::Output(text) => egui::RichText::new(text).monospace().into(),
+                        ResultView::Error(text) => egui::RichText::new(text).color(egui::Color32::LIGHT_RED).into(),
+                        ResultView::TranslationFailed(error) => {
+                            egui::RichText::new(format!("⚠ {}", error.summary())).color(egui::Color32::LIGHT_RED).into()
+                        }
+                        ResultView::Stream { text, done, .. } => {
+                            let cursor = if *done { "" } else { " ▍" };
+                            egui::RichText::new(format!("{}{}", text, cursor)).color(egui::Color32::WHITE).into()
+                        }
+                        ResultView::RoundTrip(round_trip) => {
+                            let color = if round_trip.similarity >= 0.6 {
+                                egui::Color32::LIGHT_GREEN
+                            } else if round_trip.similarity >= 0.35 {
+                                egui::Color32::YELLOW
+                            } else {
+                                egui::Color32::LIGHT_RED
+                            };
+                            egui::RichText::new(format!("● 一致度 {:.0}%", round_trip.similarity * 100.0)).color(color).into()
+                        }
+                        ResultView::Comparison(sections) => {
+                            let done = sections.iter().filter(|(_, slot)| slot.is_some()).count();
+                            egui::RichText::new(format!("比較 {}/{}", done, sections.len())).color(egui::Color32::GRAY).into()
+                        }
+                    };
+                    // Wrap text if too long; command output can be long, so scroll it
+                    egui::ScrollArea::vertical()
+                        .max_height(200.0)
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            let label = ui.add(egui::Label::new(text).wrap(true));
+                            if let ResultView::Translation(translation) = result {
+                                Self::translation_details_ui(translation, ui);
+                            }
+                            if let ResultView::RoundTrip(round_trip) = result {
+                                Self::round_trip_ui(round_trip, ui);
+                            }
+                            if let ResultView::Comparison(sections) = result {
+                                for (provider, slot) in sections {
+                                    if let Some(section_command) = Self::comparison_section_ui(provider, slot, ui) {
+                                        command = Some(section_command);
+                                    }
+                                }
+                            }
+                            if let ResultView::TranslationFailed(error) = result {
+                                label.on_hover_text(error.to_string());
+                                if error.is_retryable() && ui.button("↻ Retry").clicked() {
+                                    command = Some(ResultCommand::Retry);
+                                }
+                            }
+                            if let ResultView::Stream { error: Some(error), .. } = result {
+                                ui.label(egui::RichText::new(format!("⚠ {}", error)).color(egui::Color32::LIGHT_RED));
+                            }
+                        });
+                    if let Some(text) = result.copyable_text() {
+                        ui.horizontal(|ui| {
+                            if ui.button("📋 Copy").clicked() {
+                                command = Some(ResultCommand::Copy(text.to_string()));
+                            }
+                            if ui.button("⇄ Replace").on_hover_text("Paste over the selection").clicked() {
+                                command = Some(ResultCommand::Replace(text.to_string()));
+                            }
+                        });
+                    }
+                }
+                match command {
+                    Some(ResultCommand::Retry) => {
+                        if let Some((id, option)) = self.last_invocation.clone() {
+                            self.run_action(&id, option.as_deref(), true);
+                        }
                     }
-                    if ui.button("A文 EN").clicked() {
-                        self.translation = Some("翻訳中...".to_string());
-                        actions::translate_async(&self.selected_text, self.event_sender.clone());
+                    Some(ResultCommand::Copy(text)) => {
+                        actions::copy_selection(&text, &self.selection.app);
+                        self.hide();
                     }
-                });
+                    Some(ResultCommand::Replace(text)) => {
+                        actions::copy_selection(&text, &self.selection.app);
+                        actions::paste();
+                        self.hide();
+                    }
+                    None => {}
+                }
 
-                // Translation Result Area
-                if let Some(text) = &self.translation {
+                if self.show_settings {
                     ui.separator();
-                    // Wrap text if too long
-                    ui.add(egui::Label::new(egui::RichText::new(text).color(egui::Color32::LIGHT_BLUE)).wrap(true));
+                    self.settings_ui(ui);
                 }
 
                 ui.separator();
-                if ui.button("🚪 Quit App").clicked() {
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                }
+                ui.horizontal(|ui| {
+                    let settings_label = if self.pack_report.issues.is_empty() {
+                        egui::RichText::new("⚙")
+                    } else {
+                        egui::RichText::new(format!("⚠ {}", self.pack_report.issues.len())).color(egui::Color32::YELLOW)
+                    };
+                    if ui.button(settings_label).clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+                    if ui.button("🚪 Quit App").clicked() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
             });
         });
 
@@ -126,3 +582,40 @@ impl App for PopWinApp {
         // For PoC, we rely on "SelectionCleared" event or manual button click.
     }
 }
+
+// Translation text with glossary terms highlighted
+fn translation_text(translation: &Translation) -> egui::WidgetText {
+    let color = egui::Color32::LIGHT_BLUE;
+    if translation.glossary_terms.is_empty() {
+        return egui::RichText::new(&translation.text).color(color).into();
+    }
+
+    // Byte ranges of every term occurrence, earliest (then longest) first, overlaps dropped
+    let text = &translation.text;
+    let mut spans: Vec<(usize, usize)> = translation
+        .glossary_terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .flat_map(|term| text.match_indices(term.as_str()).map(|(start, m)| (start, start + m.len())))
+        .collect();
+    spans.sort_by_key(|&(start, end)| (start, std::cmp::Reverse(end)));
+
+    let plain = egui::TextFormat { color, ..Default::default() };
+    let highlighted = egui::TextFormat {
+        color: egui::Color32::WHITE,
+        background: egui::Color32::from_rgb(60, 70, 110),
+        ..Default::default()
+    };
+    let mut job = egui::text::LayoutJob::default();
+    let mut pos = 0;
+    for (start, end) in spans {
+        if start < pos {
+            continue;
+        }
+        job.append(&text[pos..start], 0.0, plain.clone());
+        job.append(&text[start..end], 0.0, highlighted.clone());
+        pos = end;
+    }
+    job.append(&text[pos..], 0.0, plain);
+    job.into()
+}
@@ -0,0 +1,359 @@
+// Reading the selection by sending Ctrl+C, without losing what the user had copied.
+//
+// The clipboard is snapshotted (every format, not just text), the copy is sent, and we poll
+// until the clipboard actually changes: by sequence number where the platform has one,
+// otherwise by comparing against a sentinel we put there ourselves. Whatever happened, the
+// snapshot is put back afterwards.
+
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::platform::{ClipboardBackend, ClipboardSnapshot};
+
+// Some apps take a while to answer Ctrl+C (Office, Electron); most answer within a few ms
+const FIRST_POLL: Duration = Duration::from_millis(5);
+const MAX_POLL: Duration = Duration::from_millis(40);
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(400);
+#[cfg(not(target_os = "windows"))]
+const TEXT_FORMAT: u32 = 13;
+
+enum Baseline {
+    Sequence(u64),
+    Text(Option<String>),
+}
+
+// Run `send_copy` and return the text it put on the clipboard, or None if nothing was copied
+// within `timeout`. The previous clipboard content is restored either way.
+pub fn copy_and_restore(
+    clipboard: &mut dyn ClipboardBackend,
+    send_copy: impl FnOnce(),
+    timeout: Duration,
+) -> Option<String> {
+    // Keep the probe's copy out of the clipboard history
+    crate::history::pause_watch(timeout + Duration::from_millis(500));
+    let snapshot = clipboard
+        .snapshot()
+        .map_err(|e| log::warn!("Clipboard snapshot failed, it will not be restored: {}", e))
+        .ok();
+
+    // Without a sequence number, an unchanged clipboard and a copy of the same text look alike;
+    // a sentinel tells them apart. Only when we can put the user's content back afterwards.
+    let mut touched = false;
+    let baseline = match clipboard.sequence() {
+        Some(sequence) => Baseline::Sequence(sequence),
+        None if snapshot.is_some() => {
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+            let sentinel = format!("popwin-probe-{:x}", nanos);
+            touched = clipboard.set_text(&sentinel).is_ok();
+            Baseline::Text(if touched { Some(sentinel) } else { clipboard.text() })
+        }
+        None => Baseline::Text(clipboard.text()),
+    };
+
+    send_copy();
+
+    let deadline = Instant::now() + timeout;
+    let mut interval = FIRST_POLL;
+    let mut copied = None;
+    loop {
+        thread::sleep(interval);
+        let changed = match &baseline {
+            Baseline::Sequence(before) => clipboard.sequence() != Some(*before),
+            Baseline::Text(before) => clipboard.text() != *before,
+        };
+        if changed {
+            touched = true;
+            // The sequence moves when the app empties the clipboard, before it has written the text
+            copied = clipboard.text();
+            if copied.is_some() {
+                break;
+            }
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+        interval = (interval * 2).min(MAX_POLL);
+    }
+
+    if let (true, Some(snapshot)) = (touched, &snapshot) {
+        if let Err(e) = clipboard.restore(snapshot) {
+            log::warn!("Clipboard not restored: {}", e);
+        }
+    }
+    if copied.is_none() {
+        log::info!("Nothing was copied within {:?}", timeout);
+    }
+    copied
+}
+
+// arboard's text-only view of the clipboard, for platforms without a richer backend
+#[cfg(not(target_os = "windows"))]
+pub struct SystemClipboard;
+
+#[cfg(not(target_os = "windows"))]
+impl ClipboardBackend for SystemClipboard {
+    fn sequence(&self) -> Option<u64> {
+        None
+    }
+
+    fn snapshot(&mut self) -> Result<ClipboardSnapshot, String> {
+        let formats = self.text().map(|text| vec![(TEXT_FORMAT, text.into_bytes())]).unwrap_or_default();
+        Ok(ClipboardSnapshot { formats })
+    }
+
+    fn restore(&mut self, snapshot: &ClipboardSnapshot) -> Result<(), String> {
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+        match snapshot.formats.iter().find(|(format, _)| *format == TEXT_FORMAT) {
+            Some((_, data)) => clipboard.set_text(String::from_utf8_lossy(data)),
+            None => clipboard.clear(),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    fn text(&mut self) -> Option<String> {
+        arboard::Clipboard::new().ok()?.get_text().ok()
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+        clipboard.set_text(text).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use windows_backend::WindowsClipboard;
+
+#[cfg(target_os = "windows")]
+mod windows_backend {
+    use crate::platform::{ClipboardBackend, ClipboardSnapshot};
+    use std::thread;
+    use std::time::Duration;
+    use windows::Win32::Foundation::{GlobalFree, HANDLE, HGLOBAL, HWND};
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData, GetClipboardSequenceNumber,
+        OpenClipboard, SetClipboardData,
+    };
+    use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE};
+
+    // Formats whose data is a GDI or metafile handle, or owner-drawn, rather than global memory.
+    // They can't be copied byte for byte and are left out of the snapshot.
+    const CF_BITMAP: u32 = 2;
+    const CF_METAFILEPICT: u32 = 3;
+    const CF_PALETTE: u32 = 9;
+    const CF_ENHMETAFILE: u32 = 14;
+    const CF_OWNERDISPLAY: u32 = 0x80;
+    const CF_DSPBITMAP: u32 = 0x82;
+    const CF_DSPMETAFILEPICT: u32 = 0x83;
+    const CF_DSPENHMETAFILE: u32 = 0x8E;
+    const CF_GDIOBJFIRST: u32 = 0x300;
+    const CF_GDIOBJLAST: u32 = 0x3FF;
+
+    fn is_memory_format(format: u32) -> bool {
+        !matches!(
+            format,
+            CF_BITMAP
+                | CF_METAFILEPICT
+                | CF_PALETTE
+                | CF_ENHMETAFILE
+                | CF_OWNERDISPLAY
+                | CF_DSPBITMAP
+                | CF_DSPMETAFILEPICT
+                | CF_DSPENHMETAFILE
+                | CF_GDIOBJFIRST..=CF_GDIOBJLAST
+        )
+    }
+
+    pub struct WindowsClipboard;
+
+    // OpenClipboard fails while another app has it open; retry briefly
+    struct OpenClipboardGuard;
+
+    impl OpenClipboardGuard {
+        fn open() -> Result<Self, String> {
+            for _ in 0..10 {
+                if unsafe { OpenClipboard(HWND::default()) }.is_ok() {
+                    return Ok(Self);
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err("the clipboard is in use by another application".to_string())
+        }
+    }
+
+    impl Drop for OpenClipboardGuard {
+        fn drop(&mut self) {
+            unsafe {
+                let _ = CloseClipboard();
+            }
+        }
+    }
+
+    impl ClipboardBackend for WindowsClipboard {
+        fn sequence(&self) -> Option<u64> {
+            Some(unsafe { GetClipboardSequenceNumber() } as u64)
+        }
+
+        fn snapshot(&mut self) -> Result<ClipboardSnapshot, String> {
+            let _open = OpenClipboardGuard::open()?;
+            let mut formats = Vec::new();
+            let mut format = 0;
+            loop {
+                format = unsafe { EnumClipboardFormats(format) };
+                if format == 0 {
+                    break;
+                }
+                if !is_memory_format(format) {
+                    continue;
+                }
+                unsafe {
+                    let Ok(handle) = GetClipboardData(format) else { continue };
+                    let global = HGLOBAL(handle.0);
+                    let ptr = GlobalLock(global) as *const u8;
+                    if ptr.is_null() {
+                        continue;
+                    }
+                    let data = std::slice::from_raw_parts(ptr, GlobalSize(global)).to_vec();
+                    let _ = GlobalUnlock(global);
+                    formats.push((format, data));
+                }
+            }
+            Ok(ClipboardSnapshot { formats })
+        }
+
+        fn restore(&mut self, snapshot: &ClipboardSnapshot) -> Result<(), String> {
+            let _open = OpenClipboardGuard::open()?;
+            unsafe {
+                EmptyClipboard().map_err(|e| e.to_string())?;
+                for (format, data) in &snapshot.formats {
+                    let global = GlobalAlloc(GMEM_MOVEABLE, data.len().max(1)).map_err(|e| e.to_string())?;
+                    let ptr = GlobalLock(global) as *mut u8;
+                    if ptr.is_null() {
+                        let _ = GlobalFree(global);
+                        continue;
+                    }
+                    std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+                    let _ = GlobalUnlock(global);
+                    // Once set, the memory belongs to the clipboard
+                    if SetClipboardData(*format, HANDLE(global.0)).is_err() {
+                        let _ = GlobalFree(global);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        fn text(&mut self) -> Option<String> {
+            arboard::Clipboard::new().ok()?.get_text().ok()
+        }
+
+        fn set_text(&mut self, text: &str) -> Result<(), String> {
+            let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+            clipboard.set_text(text).map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::mock::FakeClipboard;
+
+    const HTML_FORMAT: u32 = 49161;
+
+    // A clipboard like arboard's, without a change counter
+    struct Unsequenced(FakeClipboard);
+
+    impl ClipboardBackend for Unsequenced {
+        fn sequence(&self) -> Option<u64> {
+            None
+        }
+        fn snapshot(&mut self) -> Result<ClipboardSnapshot, String> {
+            self.0.snapshot()
+        }
+        fn restore(&mut self, snapshot: &ClipboardSnapshot) -> Result<(), String> {
+            self.0.restore(snapshot)
+        }
+        fn text(&mut self) -> Option<String> {
+            self.0.text()
+        }
+        fn set_text(&mut self, text: &str) -> Result<(), String> {
+            self.0.set_text(text)
+        }
+    }
+
+    // The user's clipboard: text plus a rich format
+    fn user_clipboard() -> (FakeClipboard, ClipboardSnapshot) {
+        let mut clipboard = FakeClipboard::default();
+        let snapshot = ClipboardSnapshot {
+            formats: vec![
+                (FakeClipboard::TEXT_FORMAT, b"copied earlier".to_vec()),
+                (HTML_FORMAT, b"<b>copied</b>".to_vec()),
+            ],
+        };
+        clipboard.restore(&snapshot).unwrap();
+        (clipboard, snapshot)
+    }
+
+    #[test]
+    fn returns_the_copy_and_restores_every_format() {
+        let (mut clipboard, before) = user_clipboard();
+        let app = clipboard.clone();
+        let copied = copy_and_restore(&mut clipboard, || app.put_text("selected"), PROBE_TIMEOUT);
+        assert_eq!(copied.as_deref(), Some("selected"));
+        assert_eq!(clipboard.contents(), before);
+    }
+
+    #[test]
+    fn copying_the_same_text_again_is_seen() {
+        let (mut clipboard, _) = user_clipboard();
+        let app = clipboard.clone();
+        let copied = copy_and_restore(&mut clipboard, || app.put_text("copied earlier"), PROBE_TIMEOUT);
+        assert_eq!(copied.as_deref(), Some("copied earlier"));
+    }
+
+    #[test]
+    fn slow_apps_are_waited_for() {
+        let (mut clipboard, before) = user_clipboard();
+        let app = clipboard.clone();
+        let send_copy = || {
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                app.put_text("selected");
+            });
+        };
+        assert_eq!(copy_and_restore(&mut clipboard, send_copy, PROBE_TIMEOUT).as_deref(), Some("selected"));
+        assert_eq!(clipboard.contents(), before);
+    }
+
+    #[test]
+    fn nothing_copied_times_out_and_leaves_the_clipboard_alone() {
+        let (mut clipboard, before) = user_clipboard();
+        let sequence = clipboard.sequence();
+        let timeout = Duration::from_millis(100);
+        let started = Instant::now();
+        assert_eq!(copy_and_restore(&mut clipboard, || {}, timeout), None);
+        assert!(started.elapsed() >= timeout);
+        assert!(started.elapsed() < PROBE_TIMEOUT);
+        // Not even restored, since it never changed
+        assert_eq!(clipboard.sequence(), sequence);
+        assert_eq!(clipboard.contents(), before);
+    }
+
+    #[test]
+    fn without_a_sequence_a_sentinel_detects_the_copy() {
+        let (fake, before) = user_clipboard();
+        let app = fake.clone();
+        let mut clipboard = Unsequenced(fake.clone());
+        let copied = copy_and_restore(&mut clipboard, || app.put_text("copied earlier"), PROBE_TIMEOUT);
+        assert_eq!(copied.as_deref(), Some("copied earlier"));
+        assert_eq!(fake.contents(), before);
+    }
+
+    #[test]
+    fn without_a_sequence_the_sentinel_is_cleaned_up_on_timeout() {
+        let (fake, before) = user_clipboard();
+        let mut clipboard = Unsequenced(fake.clone());
+        assert_eq!(copy_and_restore(&mut clipboard, || {}, Duration::from_millis(50)), None);
+        assert_eq!(fake.contents(), before);
+    }
+}
@@ -1,16 +1,57 @@
+mod clipboard;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
+
+pub use clipboard::{copy_and_restore, PROBE_TIMEOUT};
+#[cfg(not(target_os = "windows"))]
+pub use clipboard::SystemClipboard;
+#[cfg(target_os = "windows")]
+pub use clipboard::WindowsClipboard;
+
+use crate::platform::{self, Shortcut};
+
+// Where the clipboard content came from. `private` is the owner's request to keep it out of
+// clipboard history (password managers set it).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClipboardOrigin {
+    pub app: Option<String>,
+    pub private: bool,
+}
+
+// The application with the focus: executable name (e.g. "OUTLOOK.EXE") and top-level window class
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppInfo {
+    pub process: Option<String>,
+    pub class: Option<String>,
+}
+
+// Process names from config against the running app's, in any case and with or without
+// ".exe": "KeePassXC.exe" on Windows is "keepassxc" in /proc on Linux
+pub fn same_app(a: &str, b: &str) -> bool {
+    fn base(name: &str) -> &str {
+        let name = name.trim();
+        match name.len().checked_sub(4) {
+            Some(cut) if name.is_char_boundary(cut) && name[cut..].eq_ignore_ascii_case(".exe") => &name[..cut],
+            _ => name,
+        }
+    }
+    base(a).eq_ignore_ascii_case(base(b))
+}
+
+// Send Ctrl+C through the current injector and read the result off the clipboard, leaving the
+// user's clipboard as it was
+pub fn get_text_via_clipboard() -> Option<String> {
+    let injector = platform::injector();
+    platform::with_clipboard(|clipboard| copy_and_restore(clipboard, || injector.send(Shortcut::Copy), PROBE_TIMEOUT))
+}
+
 #[cfg(target_os = "windows")]
 mod windows_impl {
     use super::*;
-    use std::time::Duration;
-    use std::thread;
     use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, CoUninitialize};
     use windows::Win32::UI::Accessibility::{
         CUIAutomation, IUIAutomation, IUIAutomationElement, IUIAutomationTextPattern, UIA_TextPatternId,
     };
-    use windows::Win32::UI::Input::KeyboardAndMouse::{
-        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_C, VK_CONTROL,
-    };
-    use arboard::Clipboard;
 
     pub fn get_selected_text() -> Option<String> {
         if let Some(text) = get_text_via_uia() {
@@ -63,34 +104,140 @@ mod windows_impl {
         }
     }
 
-    fn get_text_via_clipboard() -> Option<String> {
-        simulate_ctrl_c();
-        thread::sleep(Duration::from_millis(100));
-        if let Ok(mut clipboard) = Clipboard::new() {
-            return clipboard.get_text().ok();
+    // Executable name of the foreground window's process, e.g. "chrome.exe"
+    pub fn foreground_app() -> Option<String> {
+        use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+        window_process_name(unsafe { GetForegroundWindow() })
+    }
+
+    // Who put the current content on the clipboard, and whether they asked monitors to skip it
+    pub fn clipboard_origin() -> ClipboardOrigin {
+        use windows::core::w;
+        use windows::Win32::System::DataExchange::{
+            GetClipboardOwner, IsClipboardFormatAvailable, RegisterClipboardFormatW,
+        };
+
+        unsafe {
+            // Set by password managers; "Clipboard Viewer Ignore" is the older convention
+            let private = [w!("ExcludeClipboardContentFromMonitorProcessing"), w!("Clipboard Viewer Ignore")]
+                .into_iter()
+                .map(|name| RegisterClipboardFormatW(name))
+                .any(|format| format != 0 && IsClipboardFormatAvailable(format).is_ok());
+            let app = GetClipboardOwner().ok().and_then(window_process_name);
+            ClipboardOrigin { app, private }
         }
-        None
     }
 
-    fn simulate_ctrl_c() {
+    fn window_process_name(hwnd: windows::Win32::Foundation::HWND) -> Option<String> {
+        use windows::core::PWSTR;
+        use windows::Win32::Foundation::CloseHandle;
+        use windows::Win32::System::Threading::{
+            OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+        };
+        use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
         unsafe {
-            let inputs = [
-                INPUT { type_: INPUT_KEYBOARD, Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: VK_CONTROL, ..Default::default() } } },
-                INPUT { type_: INPUT_KEYBOARD, Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: VK_C, ..Default::default() } } },
-                INPUT { type_: INPUT_KEYBOARD, Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: VK_C, dwFlags: KEYEVENTF_KEYUP, ..Default::default() } } },
-                INPUT { type_: INPUT_KEYBOARD, Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: VK_CONTROL, dwFlags: KEYEVENTF_KEYUP, ..Default::default() } } },
-            ];
-            SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            if pid == 0 {
+                return None;
+            }
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut buf = [0u16; 260];
+            let mut len = buf.len() as u32;
+            let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut len);
+            let _ = CloseHandle(process);
+            result.ok()?;
+            let path = String::from_utf16_lossy(&buf[..len as usize]);
+            path.rsplit(['\\', '/']).next().map(|name| name.to_string())
+        }
+    }
+
+    pub fn foreground_info() -> AppInfo {
+        use windows::Win32::UI::WindowsAndMessaging::{GetClassNameW, GetForegroundWindow};
+
+        let mut buf = [0u16; 256];
+        let len = unsafe { GetClassNameW(GetForegroundWindow(), &mut buf) };
+        let class = (len > 0).then(|| String::from_utf16_lossy(&buf[..len as usize]));
+        AppInfo { process: foreground_app(), class }
+    }
+
+    // Screen position just below the text caret of the foreground window, for apps that use the
+    // system caret (many custom-drawn editors don't)
+    pub fn caret_position() -> Option<(i32, i32)> {
+        use windows::Win32::Foundation::POINT;
+        use windows::Win32::Graphics::Gdi::ClientToScreen;
+        use windows::Win32::UI::WindowsAndMessaging::{
+            GetForegroundWindow, GetGUIThreadInfo, GetWindowThreadProcessId, GUITHREADINFO,
+        };
+
+        unsafe {
+            let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+            let mut info = GUITHREADINFO { cbSize: std::mem::size_of::<GUITHREADINFO>() as u32, ..Default::default() };
+            GetGUIThreadInfo(thread, &mut info).ok()?;
+            if info.hwndCaret.is_invalid() {
+                return None;
+            }
+            let mut point = POINT { x: info.rcCaret.left, y: info.rcCaret.bottom };
+            ClientToScreen(info.hwndCaret, &mut point).as_bool().then_some((point.x, point.y))
+        }
+    }
+
+    // Scaling of the monitor under `position` (1.0 = 96 DPI)
+    pub fn dpi_scale_at(position: (i32, i32)) -> f32 {
+        use windows::Win32::Foundation::POINT;
+        use windows::Win32::Graphics::Gdi::{MonitorFromPoint, MONITOR_DEFAULTTONEAREST};
+        use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+
+        unsafe {
+            let monitor = MonitorFromPoint(POINT { x: position.0, y: position.1 }, MONITOR_DEFAULTTONEAREST);
+            let (mut dpi_x, mut dpi_y) = (0u32, 0u32);
+            match GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) {
+                Ok(()) if dpi_x > 0 => dpi_x as f32 / 96.0,
+                _ => 1.0,
+            }
         }
     }
 }
 
 #[cfg(not(target_os = "windows"))]
 mod dummy_impl {
+    // No accessibility API here; the Ctrl+C probe works with any injector and clipboard
     pub fn get_selected_text() -> Option<String> {
-        // In simulation, hooks sends the text directly, so this might not be called by hooks dummy
-        // but if called:
-        Some("Simulation Text via Automation".to_string())
+        super::get_text_via_clipboard()
+    }
+
+    use super::AppInfo;
+
+    // The active window's app on X11; nothing elsewhere
+    pub fn foreground_info() -> AppInfo {
+        #[cfg(all(unix, not(target_os = "macos")))]
+        if let Ok(info) = super::x11::active_app() {
+            return info;
+        }
+        AppInfo::default()
+    }
+
+    pub fn foreground_app() -> Option<String> {
+        foreground_info().process
+    }
+
+    pub fn clipboard_origin() -> super::ClipboardOrigin {
+        #[cfg(all(unix, not(target_os = "macos")))]
+        if let Ok(origin) = super::x11::clipboard_origin() {
+            return origin;
+        }
+        super::ClipboardOrigin::default()
+    }
+
+    pub fn caret_position() -> Option<(i32, i32)> {
+        None
+    }
+
+    // Only scripted input reaches the gesture recognizer here
+    pub fn dpi_scale_at(_position: (i32, i32)) -> f32 {
+        1.0
     }
 }
 
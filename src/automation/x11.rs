@@ -0,0 +1,106 @@
+// The active window on X11, from the window manager's _NET_ACTIVE_WINDOW: its WM_CLASS, and
+// its process name through _NET_WM_PID and /proc. Clients on other hosts have no local process.
+// Also who owns CLIPBOARD, for the clipboard history.
+
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, CreateWindowAux, Window, WindowClass};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use super::{AppInfo, ClipboardOrigin};
+
+const TARGETS_TIMEOUT: Duration = Duration::from_millis(200);
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_PID,
+        CLIPBOARD,
+        TARGETS,
+        POPWIN_TARGETS,
+        // KeePassXC and KDE apps offer this target ("secret") for passwords
+        PASSWORD_HINT: b"x-kde-passwordManagerHint",
+    }
+}
+
+fn process_of(conn: &RustConnection, window: Window, atoms: &Atoms) -> Result<Option<String>, Box<dyn Error>> {
+    let pid = conn.get_property(false, window, atoms._NET_WM_PID, AtomEnum::CARDINAL, 0, 1)?.reply()?;
+    Ok(pid
+        .value32()
+        .and_then(|mut v| v.next())
+        .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+        .map(|comm| comm.trim().to_string()))
+}
+
+// The CLIPBOARD owner's process (when its owner window carries a pid) and whether it marked
+// the content as a password
+pub fn clipboard_origin() -> Result<ClipboardOrigin, Box<dyn Error>> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let atoms = Atoms::new(&conn)?.reply()?;
+
+    let owner = conn.get_selection_owner(atoms.CLIPBOARD)?.reply()?.owner;
+    if owner == x11rb::NONE {
+        return Ok(ClipboardOrigin::default());
+    }
+    let app = process_of(&conn, owner, &atoms)?;
+
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )?;
+    conn.convert_selection(window, atoms.CLIPBOARD, atoms.TARGETS, atoms.POPWIN_TARGETS, x11rb::CURRENT_TIME)?;
+    conn.flush()?;
+    let deadline = Instant::now() + TARGETS_TIMEOUT;
+    let notify = loop {
+        match conn.poll_for_event()? {
+            Some(Event::SelectionNotify(e)) if e.requestor == window => break e,
+            Some(_) => {}
+            // An owner that doesn't answer is treated as not private, like one without the hint
+            None if Instant::now() >= deadline => return Ok(ClipboardOrigin { app, private: false }),
+            None => thread::sleep(Duration::from_millis(5)),
+        }
+    };
+    let private = notify.property != x11rb::NONE && {
+        let targets = conn.get_property(true, window, atoms.POPWIN_TARGETS, AtomEnum::ATOM, 0, 1024)?.reply()?;
+        targets.value32().is_some_and(|mut atoms_offered| atoms_offered.any(|a| a == atoms.PASSWORD_HINT))
+    };
+    Ok(ClipboardOrigin { app, private })
+}
+
+pub fn active_app() -> Result<AppInfo, Box<dyn Error>> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let atoms = Atoms::new(&conn)?.reply()?;
+
+    let active = conn.get_property(false, root, atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW, 0, 1)?.reply()?;
+    let Some(window) = active.value32().and_then(|mut v| v.next()).filter(|&w| w != x11rb::NONE) else {
+        return Ok(AppInfo::default());
+    };
+
+    // "instance\0class\0"; the class is the stable, capitalized half (e.g. "Gnome-terminal")
+    let wm_class = conn.get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)?.reply()?;
+    let class = wm_class
+        .value
+        .split(|&b| b == 0)
+        .nth(1)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned());
+
+    let process = process_of(&conn, window, &atoms)?;
+    Ok(AppInfo { process, class })
+}
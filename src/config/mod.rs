@@ -0,0 +1,196 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::actions::SearchEngine;
+use crate::history::HistoryConfig;
+use crate::hooks::gesture::SelectionConfig;
+use crate::hotkeys::HotkeyConfig;
+use crate::llm::LlmConfig;
+use crate::profiles::AppRule;
+use crate::translation::TranslationConfig;
+
+const CONFIG_FILE: &str = "config.toml";
+const ACTIONS_DIR: &str = "actions";
+
+// A problem found while reading config or action packs.
+// These never abort startup; they are logged and listed in the settings view.
+#[derive(Debug, Clone)]
+pub struct LoadIssue {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl LoadIssue {
+    pub fn new(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self { path: path.into(), message: message.into() }
+    }
+}
+
+impl fmt::Display for LoadIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub actions: ActionsConfig,
+    pub search: SearchConfig,
+    pub translation: TranslationConfig,
+    pub llm: LlmConfig,
+    pub history: HistoryConfig,
+    pub selection: SelectionConfig,
+    pub hotkeys: HotkeyConfig,
+    // [[apps]] rules, first match wins
+    pub apps: Vec<AppRule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActionsConfig {
+    // Team-wide pack directory (e.g. a network share), loaded after the user's own packs
+    pub shared_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    // Extra engines (e.g. the team wiki); an engine with a built-in id replaces it
+    pub engines: Vec<SearchEngine>,
+    // Engine ids that get their own toolbar button; empty means the first `max_buttons`
+    pub toolbar: Vec<String>,
+    pub max_buttons: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            engines: Vec::new(),
+            toolbar: Vec::new(),
+            max_buttons: 1,
+        }
+    }
+}
+
+impl Config {
+    // Missing file means defaults; a broken file is reported and also falls back to defaults
+    pub fn load() -> (Config, Option<LoadIssue>) {
+        let Some(dir) = config_dir() else {
+            return (Config::default(), None);
+        };
+        let path = dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return (Config::default(), None);
+        }
+        match Self::load_from(&path) {
+            Ok(config) => (config, None),
+            // A mistake elsewhere shouldn't bring the toolbar back in apps it was turned off for
+            Err(issue) => {
+                let apps = std::fs::read_to_string(&path).map(|content| salvage_apps(&content)).unwrap_or_default();
+                (Config { apps, ..Config::default() }, Some(issue))
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config, LoadIssue> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| LoadIssue::new(path, format!("cannot read file: {}", e)))?;
+        toml::from_str(&content).map_err(|e| LoadIssue::new(path, describe_toml_error(&content, &e)))
+    }
+
+    // Directories scanned for action packs, in load order
+    pub fn action_pack_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        if let Some(dir) = config_dir() {
+            dirs.push(dir.join(ACTIONS_DIR));
+        }
+        if let Some(shared) = std::env::var_os("POPWIN_SHARED_ACTIONS") {
+            dirs.push(PathBuf::from(shared));
+        } else if let Some(shared) = &self.actions.shared_dir {
+            dirs.push(shared.clone());
+        }
+        dirs
+    }
+}
+
+// The [[apps]] rules of a config that failed to load as a whole, when they are valid by themselves
+fn salvage_apps(content: &str) -> Vec<AppRule> {
+    #[derive(Deserialize)]
+    struct AppsOnly {
+        #[serde(default)]
+        apps: Vec<AppRule>,
+    }
+    match toml::from_str::<AppsOnly>(content) {
+        Ok(config) => config.apps,
+        Err(e) => {
+            log::warn!("[[apps]] rules could not be recovered either: {}", describe_toml_error(content, &e));
+            Vec::new()
+        }
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().map(|d| d.join(CONFIG_FILE)).unwrap_or_else(|| PathBuf::from(CONFIG_FILE))
+}
+
+// Disposable data such as the translation cache. Follows POPWIN_CONFIG_DIR when it is set.
+pub fn cache_dir() -> Option<PathBuf> {
+    match std::env::var_os("POPWIN_CONFIG_DIR") {
+        Some(dir) => Some(PathBuf::from(dir).join("cache")),
+        None => dirs::cache_dir().map(|d| d.join("popwin")),
+    }
+}
+
+// Data worth keeping, such as the clipboard history. Follows POPWIN_CONFIG_DIR when it is set.
+pub fn data_dir() -> Option<PathBuf> {
+    match std::env::var_os("POPWIN_CONFIG_DIR") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => dirs::data_dir().map(|d| d.join("popwin")),
+    }
+}
+
+// %APPDATA%\popwin on Windows, ~/.config/popwin on Linux. POPWIN_CONFIG_DIR overrides it.
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("POPWIN_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::config_dir().map(|d| d.join("popwin")))
+}
+
+// "line 3: unknown field `foo`" reads better in the settings view than toml's multi-line snippet
+pub fn describe_toml_error(content: &str, error: &toml::de::Error) -> String {
+    match error.span() {
+        Some(span) => {
+            let line = content[..span.start.min(content.len())].matches('\n').count() + 1;
+            format!("line {}: {}", line, error.message())
+        }
+        None => error.message().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apps_rules_survive_errors_elsewhere() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        let content = "[selection]\nno_such_option = 1\n\n[[apps]]\napp = \"KeePass\"\nenabled = false\n";
+        std::fs::write(&path, content).unwrap();
+        assert!(Config::load_from(&path).is_err());
+
+        let apps = salvage_apps(content);
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].app.as_deref(), Some("KeePass"));
+        assert!(!apps[0].enabled);
+    }
+
+    #[test]
+    fn broken_apps_rules_are_not_recovered() {
+        assert!(salvage_apps("[[apps]]\napp = \"KeePass\"\nenable = false\n").is_empty());
+        assert!(salvage_apps("[[apps]\n").is_empty());
+    }
+}
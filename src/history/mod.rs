@@ -0,0 +1,242 @@
+// Clipboard history: text copied through PopWin and, with `watch`, anywhere else.
+// Newest first, deduplicated, capped by count and size, persisted as JSON in the data directory.
+//
+//   [history]
+//   max_entries = 200
+//   max_bytes = 1000000
+//   watch = true                       # also record copies made in other apps (off by default)
+//   ignore_apps = ["KeePassXC"]        # never record from these (password managers)
+//
+// Content its owner marks as private (password managers set a clipboard format for this) is
+// never recorded.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::automation::AppInfo;
+use crate::config::LoadIssue;
+
+const HISTORY_FILE: &str = "history.json";
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub max_entries: usize,
+    // Total text size kept on disk; the oldest entries go first
+    pub max_bytes: usize,
+    pub watch: bool,
+    // Process names (e.g. "KeePassXC"), any case, ".exe" optional; copies from them are never recorded
+    pub ignore_apps: Vec<String>,
+    // Defaults to history.json in the user data directory
+    pub path: Option<PathBuf>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 200,
+            max_bytes: 1_000_000,
+            // Off unless asked for: it keeps everything copied anywhere in a plain file
+            watch: false,
+            ignore_apps: ["KeePass.exe", "KeePassXC.exe", "1Password.exe", "Bitwarden.exe"]
+                .map(String::from)
+                .to_vec(),
+            path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub text: String,
+    // Executable name of the foreground app when the text was copied
+    pub source_app: Option<String>,
+    // Unix seconds
+    pub copied_at: u64,
+}
+
+pub struct ClipboardHistory {
+    config: HistoryConfig,
+    path: Option<PathBuf>,
+    entries: Vec<HistoryEntry>,
+    next_id: u64,
+}
+
+impl ClipboardHistory {
+    // A missing file is an empty history; an unreadable one is reported and started over
+    pub fn open(config: &HistoryConfig, path: Option<PathBuf>) -> (Self, Option<String>) {
+        let mut history = Self { config: config.clone(), path, entries: Vec::new(), next_id: 1 };
+        let Some(path) = history.path.clone().filter(|p| p.exists()) else {
+            return (history, None);
+        };
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str::<Vec<HistoryEntry>>(&content).map_err(|e| e.to_string()));
+        match loaded {
+            Ok(entries) => {
+                history.next_id = entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+                history.entries = entries;
+                history.enforce_caps();
+                (history, None)
+            }
+            Err(e) => (history, Some(format!("cannot read {}: {}; starting a new history", path.display(), e))),
+        }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    // Returns false when the text was skipped (blank, too large, or from an ignored app)
+    pub fn record(&mut self, text: &str, source_app: Option<String>) -> bool {
+        if text.trim().is_empty() || text.len() > self.config.max_bytes {
+            return false;
+        }
+        if let Some(app) = &source_app {
+            if self.config.ignore_apps.iter().any(|ignored| crate::automation::same_app(ignored, app)) {
+                return false;
+            }
+        }
+        // Copying the same text again moves it to the top
+        self.entries.retain(|e| e.text != text);
+        self.entries.insert(0, HistoryEntry { id: self.next_id, text: text.to_string(), source_app, copied_at: now() });
+        self.next_id += 1;
+        self.enforce_caps();
+        if let Err(e) = self.save() {
+            log::warn!("Clipboard history not saved: {}", e);
+        }
+        true
+    }
+
+    fn enforce_caps(&mut self) {
+        self.entries.truncate(self.config.max_entries);
+        let mut total = 0;
+        let keep = self
+            .entries
+            .iter()
+            .take_while(|e| {
+                total += e.text.len();
+                total <= self.config.max_bytes
+            })
+            .count();
+        self.entries.truncate(keep);
+    }
+
+    // Write to a temporary file first so a crash can't leave a truncated history
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string(&self.entries).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(|e| format!("cannot write {}: {}", tmp.display(), e))?;
+        std::fs::rename(&tmp, path).map_err(|e| format!("cannot replace {}: {}", path.display(), e))
+    }
+}
+
+static HISTORY: Lazy<Mutex<Option<ClipboardHistory>>> = Lazy::new(|| Mutex::new(None));
+// Last clipboard text the watcher saw, and a window in which it ignores changes
+// (PopWin's own Ctrl+C probe is not something the user copied)
+static LAST_SEEN: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+static PAUSED_UNTIL: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn init(config: &HistoryConfig) -> Option<LoadIssue> {
+    if !config.enabled {
+        return None;
+    }
+    let path = config
+        .path
+        .clone()
+        .or_else(|| crate::config::data_dir().map(|d| d.join(HISTORY_FILE)));
+    let (history, issue) = ClipboardHistory::open(config, path.clone());
+    *HISTORY.lock().unwrap() = Some(history);
+    if config.watch {
+        start_watcher();
+    }
+    issue.map(|message| LoadIssue::new(path.as_deref().unwrap_or(Path::new(HISTORY_FILE)), message))
+}
+
+pub fn is_enabled() -> bool {
+    HISTORY.lock().unwrap().is_some()
+}
+
+pub fn record(text: &str, source_app: &AppInfo) {
+    mark_seen(text);
+    // An app PopWin is turned off for ([[apps]] enabled = false) keeps its copies out too
+    if !crate::profiles::is_enabled(source_app) {
+        return;
+    }
+    if let Some(history) = HISTORY.lock().unwrap().as_mut() {
+        history.record(text, source_app.process.clone());
+    }
+}
+
+pub fn entries() -> Vec<HistoryEntry> {
+    HISTORY.lock().unwrap().as_ref().map(|h| h.entries().to_vec()).unwrap_or_default()
+}
+
+pub fn find(id: u64) -> Option<HistoryEntry> {
+    HISTORY.lock().unwrap().as_ref()?.entries().iter().find(|e| e.id == id).cloned()
+}
+
+// Clipboard content PopWin put there or read itself; the watcher won't record it
+pub fn mark_seen(text: &str) {
+    *LAST_SEEN.lock().unwrap() = Some(text.to_string());
+}
+
+pub fn pause_watch(duration: Duration) {
+    *PAUSED_UNTIL.lock().unwrap() = Some(Instant::now() + duration);
+}
+
+// Poll the clipboard; arboard has no change notification on every platform
+fn start_watcher() {
+    thread::spawn(|| {
+        let Ok(mut clipboard) = arboard::Clipboard::new() else {
+            log::warn!("Clipboard unavailable; history only records PopWin's own copies");
+            return;
+        };
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            if PAUSED_UNTIL.lock().unwrap().is_some_and(|until| Instant::now() < until) {
+                continue;
+            }
+            let Ok(text) = clipboard.get_text() else { continue };
+            let changed = LAST_SEEN.lock().unwrap().as_deref() != Some(text.as_str());
+            if !changed {
+                continue;
+            }
+            // The clipboard's owner, not the window focused up to a poll interval later
+            let origin = crate::automation::clipboard_origin();
+            if origin.private {
+                mark_seen(&text);
+                continue;
+            }
+            record(&text, &AppInfo { process: origin.app, class: None });
+        }
+    });
+}
+
+// "今", "5分前", "3時間前", "2日前"
+pub fn format_age(copied_at: u64) -> String {
+    let age = now().saturating_sub(copied_at);
+    match age {
+        0..=59 => "今".to_string(),
+        60..=3599 => format!("{}分前", age / 60),
+        3600..=86_399 => format!("{}時間前", age / 3600),
+        _ => format!("{}日前", age / 86_400),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
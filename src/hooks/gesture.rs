@@ -0,0 +1,419 @@
+// Which mouse and keyboard input amounts to "the user just selected text".
+//
+// A pure state machine: the hook feeds it input with timestamps and gets back whether to
+// hide the toolbar or to fetch the selection. Recognized gestures:
+//   drag              button down, move past the threshold, button up
+//   double/triple     repeated clicks on the same spot (word / line selection)
+//   Shift+click       extends the selection to the click
+//   Shift+arrows      keyboard selection, reported when Shift is released
+//   Ctrl+A            select all, reported when Ctrl is released
+// Gestures made with a modifier held are reported once the modifiers are released, so the
+// Ctrl+C probe that follows isn't mixed with the user's own Shift or Ctrl.
+//
+// The thresholds come from config, in pixels at 100% scaling, with per-app overrides:
+//
+//   [selection]
+//   drag_distance = 5           # pixels the pointer must travel for a drag
+//   drag_time_ms = 100          # ...and for how long the button must be held
+//   multi_click_time_ms = 500   # double/triple-click: max time between clicks
+//   multi_click_distance = 4    # ...and max distance between them
+//   settle_delay_ms = 50        # wait before asking the app for its selection
+//
+//   [[selection.apps]]
+//   app = "WINWORD.EXE"         # process name, any case, ".exe" optional
+//   settle_delay_ms = 150
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelectionConfig {
+    pub drag_distance: i32,
+    pub drag_time_ms: u64,
+    pub multi_click_time_ms: u64,
+    pub multi_click_distance: i32,
+    pub settle_delay_ms: u64,
+    pub apps: Vec<AppThresholds>,
+}
+
+impl Default for SelectionConfig {
+    fn default() -> Self {
+        let defaults = Thresholds::default();
+        Self {
+            drag_distance: defaults.drag_distance,
+            drag_time_ms: defaults.drag_time_ms,
+            multi_click_time_ms: defaults.multi_click_time_ms,
+            multi_click_distance: defaults.multi_click_distance,
+            settle_delay_ms: defaults.settle_delay_ms,
+            apps: Vec::new(),
+        }
+    }
+}
+
+// Overrides for one application; unset fields keep the [selection] value
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppThresholds {
+    pub app: String,
+    pub drag_distance: Option<i32>,
+    pub drag_time_ms: Option<u64>,
+    pub multi_click_time_ms: Option<u64>,
+    pub multi_click_distance: Option<i32>,
+    pub settle_delay_ms: Option<u64>,
+}
+
+impl SelectionConfig {
+    // Thresholds for `app` (a process name), before DPI scaling
+    pub fn thresholds(&self, app: Option<&str>) -> Thresholds {
+        let mut thresholds = Thresholds {
+            drag_distance: self.drag_distance,
+            drag_time_ms: self.drag_time_ms,
+            multi_click_time_ms: self.multi_click_time_ms,
+            multi_click_distance: self.multi_click_distance,
+            settle_delay_ms: self.settle_delay_ms,
+        };
+        let Some(app) = app else { return thresholds };
+        for o in self.apps.iter().filter(|o| crate::automation::same_app(&o.app, app)) {
+            thresholds.drag_distance = o.drag_distance.unwrap_or(thresholds.drag_distance);
+            thresholds.drag_time_ms = o.drag_time_ms.unwrap_or(thresholds.drag_time_ms);
+            thresholds.multi_click_time_ms = o.multi_click_time_ms.unwrap_or(thresholds.multi_click_time_ms);
+            thresholds.multi_click_distance = o.multi_click_distance.unwrap_or(thresholds.multi_click_distance);
+            thresholds.settle_delay_ms = o.settle_delay_ms.unwrap_or(thresholds.settle_delay_ms);
+        }
+        thresholds
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thresholds {
+    pub drag_distance: i32,
+    pub drag_time_ms: u64,
+    pub multi_click_time_ms: u64,
+    pub multi_click_distance: i32,
+    pub settle_delay_ms: u64,
+}
+
+// Windows' default double-click time and rectangle for the multi-click values
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            drag_distance: 5,
+            drag_time_ms: 100,
+            multi_click_time_ms: 500,
+            multi_click_distance: 4,
+            settle_delay_ms: 50,
+        }
+    }
+}
+
+impl Thresholds {
+    // Pixel distances for a monitor at `scale` (1.0 = 96 DPI); times are unaffected
+    pub fn scaled(self, scale: f32) -> Self {
+        let scale_px = |px: i32| (px as f32 * scale).round() as i32;
+        Self {
+            drag_distance: scale_px(self.drag_distance),
+            multi_click_distance: scale_px(self.multi_click_distance),
+            ..self
+        }
+    }
+
+    pub fn settle_delay(&self) -> Duration {
+        Duration::from_millis(self.settle_delay_ms)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Key {
+    Shift,
+    Control,
+    // Arrows, Home/End, Page Up/Down
+    Navigation,
+    A,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Move { x: i32, y: i32 },
+    ButtonDown { x: i32, y: i32 },
+    ButtonUp { x: i32, y: i32 },
+    KeyDown(Key),
+    KeyUp(Key),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionGesture {
+    Drag,
+    DoubleClick,
+    TripleClick,
+    ShiftClick,
+    Keyboard,
+    SelectAll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GestureOutput {
+    // Whatever was selected is gone (new click, typing, caret moved)
+    Cleared,
+    Selected { gesture: SelectionGesture, position: (i32, i32) },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    x: i32,
+    y: i32,
+    at_ms: u64,
+    shift: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    thresholds: Thresholds,
+    shift: bool,
+    control: bool,
+    pointer: (i32, i32),
+    press: Option<Press>,
+    // Clicks in the current multi-click series and where/when the last one ended
+    clicks: u32,
+    last_click: Option<(i32, i32, u64)>,
+    // A gesture made with modifiers held, reported when they are released
+    pending: Option<SelectionGesture>,
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        self.thresholds
+    }
+
+    // Takes effect from the next event; a press already in progress is judged by the new values
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    // Last known pointer position
+    pub fn pointer(&self) -> (i32, i32) {
+        self.pointer
+    }
+
+    // `at_ms` is any monotonic millisecond clock (the hook's event time, or elapsed time)
+    pub fn feed(&mut self, event: InputEvent, at_ms: u64) -> Option<GestureOutput> {
+        match event {
+            InputEvent::Move { x, y } => {
+                self.pointer = (x, y);
+                None
+            }
+            InputEvent::ButtonDown { x, y } => {
+                self.pointer = (x, y);
+                // A click replaces whatever the keyboard selected and is waiting on a modifier release
+                self.pending = None;
+                let t = self.thresholds;
+                let continues_series = self.last_click.is_some_and(|(cx, cy, ct)| {
+                    at_ms.saturating_sub(ct) <= t.multi_click_time_ms
+                        && (x - cx).abs() <= t.multi_click_distance
+                        && (y - cy).abs() <= t.multi_click_distance
+                });
+                self.clicks = if continues_series { self.clicks + 1 } else { 1 };
+                self.press = Some(Press { x, y, at_ms, shift: self.shift });
+                // The second click of a double-click and Shift+click keep working on the selection
+                (self.clicks == 1 && !self.shift).then_some(GestureOutput::Cleared)
+            }
+            InputEvent::ButtonUp { x, y } => {
+                self.pointer = (x, y);
+                let press = self.press.take()?;
+                let distance = (((x - press.x).pow(2) + (y - press.y).pow(2)) as f64).sqrt() as i32;
+                let elapsed = at_ms.saturating_sub(press.at_ms);
+                let gesture = if distance > self.thresholds.drag_distance && elapsed > self.thresholds.drag_time_ms {
+                    self.clicks = 0;
+                    self.last_click = None;
+                    SelectionGesture::Drag
+                } else {
+                    self.last_click = Some((x, y, at_ms));
+                    match (press.shift, self.clicks) {
+                        (true, _) => SelectionGesture::ShiftClick,
+                        (false, 2) => SelectionGesture::DoubleClick,
+                        (false, n) if n >= 3 => SelectionGesture::TripleClick,
+                        // A plain click only places the caret
+                        _ => return None,
+                    }
+                };
+                self.report(gesture)
+            }
+            InputEvent::KeyDown(key) => match key {
+                Key::Shift => {
+                    self.shift = true;
+                    None
+                }
+                Key::Control => {
+                    self.control = true;
+                    None
+                }
+                Key::Navigation if self.shift => {
+                    // Hide the toolbar while the selection is being extended
+                    let first = self.pending != Some(SelectionGesture::Keyboard);
+                    self.pending = Some(SelectionGesture::Keyboard);
+                    first.then_some(GestureOutput::Cleared)
+                }
+                Key::A if self.control => {
+                    self.pending = Some(SelectionGesture::SelectAll);
+                    None
+                }
+                // Shortcuts like Ctrl+C leave the selection alone
+                _ if self.control => None,
+                // Typing replaces the selection; moving the caret collapses it
+                _ => {
+                    self.pending = None;
+                    Some(GestureOutput::Cleared)
+                }
+            },
+            InputEvent::KeyUp(key) => {
+                match key {
+                    Key::Shift => self.shift = false,
+                    Key::Control => self.control = false,
+                    _ => return None,
+                }
+                if self.shift || self.control {
+                    return None;
+                }
+                self.pending.take().map(|gesture| GestureOutput::Selected { gesture, position: self.pointer })
+            }
+        }
+    }
+
+    fn report(&mut self, gesture: SelectionGesture) -> Option<GestureOutput> {
+        if self.shift || self.control {
+            self.pending = Some(gesture);
+            return None;
+        }
+        Some(GestureOutput::Selected { gesture, position: self.pointer })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use InputEvent::{ButtonDown, ButtonUp, KeyDown, KeyUp, Move};
+
+    fn selected(gesture: SelectionGesture, position: (i32, i32)) -> Option<GestureOutput> {
+        Some(GestureOutput::Selected { gesture, position })
+    }
+
+    // Feed (ms, event) pairs and keep what came out
+    fn run(events: &[(u64, InputEvent)]) -> Vec<GestureOutput> {
+        let mut recognizer = GestureRecognizer::new();
+        events.iter().filter_map(|&(at_ms, event)| recognizer.feed(event, at_ms)).collect()
+    }
+
+    #[test]
+    fn drag_selects_at_the_release() {
+        let outputs = run(&[
+            (0, ButtonDown { x: 10, y: 10 }),
+            (150, Move { x: 80, y: 12 }),
+            (160, ButtonUp { x: 80, y: 12 }),
+        ]);
+        let drag = GestureOutput::Selected { gesture: SelectionGesture::Drag, position: (80, 12) };
+        assert_eq!(outputs, [GestureOutput::Cleared, drag]);
+    }
+
+    #[test]
+    fn short_or_quick_moves_are_clicks() {
+        // Jitter below the distance, and a flick faster than the drag time
+        let jitter = run(&[(0, ButtonDown { x: 10, y: 10 }), (300, ButtonUp { x: 13, y: 11 })]);
+        assert_eq!(jitter, [GestureOutput::Cleared]);
+        let flick = run(&[(0, ButtonDown { x: 10, y: 10 }), (40, ButtonUp { x: 90, y: 10 })]);
+        assert_eq!(flick, [GestureOutput::Cleared]);
+    }
+
+    #[test]
+    fn double_and_triple_click() {
+        let mut recognizer = GestureRecognizer::new();
+        let mut click = |at_ms| {
+            recognizer.feed(ButtonDown { x: 50, y: 50 }, at_ms);
+            recognizer.feed(ButtonUp { x: 51, y: 50 }, at_ms + 60)
+        };
+        assert_eq!(click(0), None);
+        assert_eq!(click(200), selected(SelectionGesture::DoubleClick, (51, 50)));
+        assert_eq!(click(400), selected(SelectionGesture::TripleClick, (51, 50)));
+    }
+
+    #[test]
+    fn clicks_too_far_apart_in_time_start_a_new_series() {
+        let outputs = run(&[
+            (0, ButtonDown { x: 50, y: 50 }),
+            (60, ButtonUp { x: 50, y: 50 }),
+            (1000, ButtonDown { x: 50, y: 50 }),
+            (1060, ButtonUp { x: 50, y: 50 }),
+        ]);
+        assert_eq!(outputs, [GestureOutput::Cleared, GestureOutput::Cleared]);
+    }
+
+    #[test]
+    fn shift_click_is_reported_when_shift_is_released() {
+        let mut recognizer = GestureRecognizer::new();
+        assert_eq!(recognizer.feed(KeyDown(Key::Shift), 0), None);
+        // Extends the selection, so nothing is cleared
+        assert_eq!(recognizer.feed(ButtonDown { x: 200, y: 40 }, 100), None);
+        assert_eq!(recognizer.feed(ButtonUp { x: 200, y: 40 }, 150), None);
+        assert_eq!(recognizer.feed(KeyUp(Key::Shift), 300), selected(SelectionGesture::ShiftClick, (200, 40)));
+    }
+
+    #[test]
+    fn shift_arrows_hide_the_toolbar_then_select() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.feed(Move { x: 30, y: 30 }, 0);
+        recognizer.feed(KeyDown(Key::Shift), 10);
+        assert_eq!(recognizer.feed(KeyDown(Key::Navigation), 20), Some(GestureOutput::Cleared));
+        assert_eq!(recognizer.feed(KeyUp(Key::Navigation), 60), None);
+        assert_eq!(recognizer.feed(KeyDown(Key::Navigation), 100), None);
+        assert_eq!(recognizer.feed(KeyUp(Key::Shift), 200), selected(SelectionGesture::Keyboard, (30, 30)));
+    }
+
+    #[test]
+    fn ctrl_a_selects_all_and_ctrl_c_keeps_the_selection() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.feed(KeyDown(Key::Control), 0);
+        assert_eq!(recognizer.feed(KeyDown(Key::A), 10), None);
+        assert_eq!(recognizer.feed(KeyUp(Key::A), 50), None);
+        assert_eq!(recognizer.feed(KeyUp(Key::Control), 80), selected(SelectionGesture::SelectAll, (0, 0)));
+
+        recognizer.feed(KeyDown(Key::Control), 100);
+        assert_eq!(recognizer.feed(KeyDown(Key::Other), 110), None);
+        assert_eq!(recognizer.feed(KeyUp(Key::Control), 150), None);
+    }
+
+    #[test]
+    fn a_click_drops_a_pending_keyboard_selection() {
+        let outputs = run(&[
+            (0, KeyDown(Key::Control)),
+            (10, KeyDown(Key::A)),
+            (50, KeyUp(Key::A)),
+            (100, ButtonDown { x: 40, y: 40 }),
+            (150, ButtonUp { x: 40, y: 40 }),
+            (200, KeyUp(Key::Control)),
+        ]);
+        assert_eq!(outputs, [GestureOutput::Cleared]);
+
+        let outputs = run(&[
+            (0, KeyDown(Key::Shift)),
+            (10, KeyDown(Key::Navigation)),
+            (50, KeyUp(Key::Navigation)),
+            (100, ButtonDown { x: 40, y: 40 }),
+            (150, ButtonUp { x: 40, y: 40 }),
+            (200, KeyUp(Key::Shift)),
+        ]);
+        let shift_click = GestureOutput::Selected { gesture: SelectionGesture::ShiftClick, position: (40, 40) };
+        assert_eq!(outputs, [GestureOutput::Cleared, shift_click]);
+    }
+
+    #[test]
+    fn typing_clears_the_selection() {
+        assert_eq!(run(&[(0, KeyDown(Key::Other))]), [GestureOutput::Cleared]);
+        assert_eq!(run(&[(0, KeyDown(Key::Navigation))]), [GestureOutput::Cleared]);
+    }
+}
@@ -1,134 +1,248 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
 use crossbeam_channel::Sender;
+use once_cell::sync::Lazy;
+
 use crate::AppEvent;
 
+pub mod gesture;
+pub mod replay;
+
+use gesture::{GestureOutput, GestureRecognizer, InputEvent, Key, SelectionConfig};
+
+static SELECTION: Lazy<Mutex<SelectionConfig>> = Lazy::new(|| Mutex::new(SelectionConfig::default()));
+
+// Gesture thresholds from the [selection] config section; defaults until this is called
+pub fn configure(config: &SelectionConfig) {
+    *SELECTION.lock().unwrap() = config.clone();
+}
+
 #[cfg(target_os = "windows")]
 mod windows_impl {
-    use super::*;
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::{Arc, Mutex};
+    use super::gesture::{GestureRecognizer, InputEvent, Key};
+    use crossbeam_channel::{Receiver, Sender};
+    use crate::platform::SelectionSource;
+    use crate::AppEvent;
+    use std::sync::Mutex;
     use std::thread;
-    use std::time::{Duration, Instant};
     use once_cell::sync::Lazy;
-    use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM, HWND};
+    use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM, HWND, POINT};
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        VIRTUAL_KEY, VK_A, VK_CONTROL, VK_DOWN, VK_END, VK_HOME, VK_LCONTROL, VK_LEFT, VK_LSHIFT, VK_NEXT, VK_PRIOR,
+        VK_RCONTROL, VK_RIGHT, VK_RSHIFT, VK_SHIFT, VK_UP,
+    };
     use windows::Win32::UI::WindowsAndMessaging::{
-        CallNextHookEx, GetMessageW, SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, MSG, WH_MOUSE_LL,
-        WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, MSLLHOOKSTRUCT,
+        CallNextHookEx, GetForegroundWindow, GetMessageW, GetWindowThreadProcessId, SetWindowsHookExW,
+        UnhookWindowsHookEx, WindowFromPoint, HHOOK, HOOKPROC, KBDLLHOOKSTRUCT, LLKHF_INJECTED, LLMHF_INJECTED, MSG,
+        MSLLHOOKSTRUCT, WH_KEYBOARD_LL, WH_MOUSE_LL, WINDOWS_HOOK_ID, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP,
+        WM_MOUSEMOVE, WM_SYSKEYDOWN, WM_SYSKEYUP,
     };
 
-    // Global state for the hook procedure
-    static HOOK_SENDER: Lazy<Mutex<Option<Sender<AppEvent>>>> = Lazy::new(|| Mutex::new(None));
-    static HOOK_HANDLE: Lazy<Mutex<Option<HHOOK>>> = Lazy::new(|| Mutex::new(None));
-    static DRAG_START: Lazy<Mutex<Option<(i32, i32, Instant)>>> = Lazy::new(|| Mutex::new(None));
+    // Global state for the hook procedures. They only queue the event: Windows drops a hook
+    // that keeps input waiting (LowLevelHooksTimeout), and recognizing a gesture looks up the
+    // foreground app and the monitor DPI.
+    static HOOK_EVENTS: Lazy<Mutex<Option<Sender<(InputEvent, u32)>>>> = Lazy::new(|| Mutex::new(None));
+    static HOOK_HANDLES: Lazy<Mutex<Vec<HHOOK>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+    // Low-level mouse and keyboard hooks feeding the gesture recognizer, then the text via UI Automation / Ctrl+C
+    pub struct MouseHookSource;
 
-    const DRAG_THRESHOLD: i32 = 5; // pixels
-    const DRAG_TIME_THRESHOLD: u128 = 100; // milliseconds
+    impl SelectionSource for MouseHookSource {
+        fn start(&mut self, sender: Sender<AppEvent>) -> Result<(), String> {
+            start_global_hook(sender);
+            Ok(())
+        }
 
-    pub fn start_global_hook(sender: Sender<AppEvent>) {
-        {
-            let mut sender_guard = HOOK_SENDER.lock().unwrap();
-            *sender_guard = Some(sender);
+        fn stop(&mut self) {
+            stop_global_hook();
         }
+    }
+
+    fn start_global_hook(sender: Sender<AppEvent>) {
+        let (events, queued) = crossbeam_channel::unbounded();
+        *HOOK_EVENTS.lock().unwrap() = Some(events);
+        thread::spawn(move || recognize(queued, sender));
 
         thread::spawn(move || {
             unsafe {
-                let hook_id = SetWindowsHookExW(WH_MOUSE_LL, Some(hook_proc), None, 0);
-                match hook_id {
-                    Ok(hhook) => {
-                        {
-                            let mut handle_guard = HOOK_HANDLE.lock().unwrap();
-                            *handle_guard = Some(hhook);
-                        }
-                        let mut msg = MSG::default();
-                        while GetMessageW(&mut msg, HWND(0), 0, 0).into() {}
+                // Both hooks are called on this thread, so it has to pump messages
+                let hooks: [(WINDOWS_HOOK_ID, HOOKPROC, &str); 2] = [
+                    (WH_MOUSE_LL, Some(mouse_proc), "WH_MOUSE_LL"),
+                    (WH_KEYBOARD_LL, Some(keyboard_proc), "WH_KEYBOARD_LL"),
+                ];
+                for (kind, proc, name) in hooks {
+                    match SetWindowsHookExW(kind, proc, None, 0) {
+                        Ok(hhook) => HOOK_HANDLES.lock().unwrap().push(hhook),
+                        Err(e) => log::error!("Failed to set {} hook: {:?}", name, e),
                     }
-                    Err(e) => log::error!("Failed to set WH_MOUSE_LL hook: {:?}", e),
                 }
+                if HOOK_HANDLES.lock().unwrap().is_empty() {
+                    return;
+                }
+                let mut msg = MSG::default();
+                while GetMessageW(&mut msg, HWND(0), 0, 0).into() {}
             }
         });
     }
 
-    pub fn stop_global_hook() {
+    fn stop_global_hook() {
+        // Ends the recognizer thread
+        *HOOK_EVENTS.lock().unwrap() = None;
         unsafe {
-            let mut handle_guard = HOOK_HANDLE.lock().unwrap();
-            if let Some(hhook) = *handle_guard {
-                UnhookWindowsHookEx(hhook);
-                *handle_guard = None;
+            for hhook in HOOK_HANDLES.lock().unwrap().drain(..) {
+                let _ = UnhookWindowsHookEx(hhook);
             }
         }
     }
 
-    unsafe extern "system" fn hook_proc(n_code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-        if n_code >= 0 {
-            let mouse_struct = *(l_param.0 as *const MSLLHOOKSTRUCT);
-            let x = mouse_struct.pt.x;
-            let y = mouse_struct.pt.y;
-
-            match w_param.0 as u32 {
-                WM_LBUTTONDOWN => {
-                    let mut start_guard = DRAG_START.lock().unwrap();
-                    *start_guard = Some((x, y, Instant::now()));
-                     if let Some(sender_guard) = HOOK_SENDER.lock().unwrap().as_ref() {
-                        let _ = sender_guard.send(AppEvent::SelectionCleared);
-                    }
+    fn feed(event: InputEvent, time_ms: u32) {
+        if let Some(events) = HOOK_EVENTS.lock().unwrap().as_ref() {
+            let _ = events.send((event, time_ms));
+        }
+    }
+
+    fn recognize(queued: Receiver<(InputEvent, u32)>, sender: Sender<AppEvent>) {
+        let mut recognizer = GestureRecognizer::new();
+        let mut own_press = false;
+        for (event, time_ms) in queued {
+            // Clicks on the toolbar (a Shift+click is a refresh there) and keys typed into it
+            // aren't selections; modifiers still count, or the recognizer loses track of them
+            let ours = match event {
+                InputEvent::ButtonDown { x, y } => {
+                    own_press = is_own_window(unsafe { WindowFromPoint(POINT { x, y }) });
+                    own_press
                 }
-                WM_MOUSEMOVE => {}
-                WM_LBUTTONUP => {
-                     let mut start_guard = DRAG_START.lock().unwrap();
-                     if let Some((start_x, start_y, start_time)) = *start_guard {
-                         let distance = ((x - start_x).pow(2) + (y - start_y).pow(2)) as f64;
-                         let distance = distance.sqrt() as i32;
-                         let elapsed = start_time.elapsed().as_millis();
-
-                         if distance > DRAG_THRESHOLD && elapsed > DRAG_TIME_THRESHOLD {
-                             if let Some(sender_guard) = HOOK_SENDER.lock().unwrap().as_ref() {
-                                let sender_clone = sender_guard.clone();
-                                thread::spawn(move || {
-                                    thread::sleep(Duration::from_millis(50)); 
-                                    if let Some(text) = crate::automation::get_selected_text() {
-                                        if !text.trim().is_empty() {
-                                            let _ = sender_clone.send(AppEvent::SelectionDetected {
-                                                text,
-                                                position: (x, y),
-                                            });
-                                        }
-                                    }
-                                });
-                             }
-                         }
-                     }
-                     *start_guard = None;
+                InputEvent::ButtonUp { .. } => std::mem::take(&mut own_press),
+                InputEvent::KeyDown(Key::Shift | Key::Control) | InputEvent::KeyUp(Key::Shift | Key::Control) => false,
+                InputEvent::KeyDown(_) | InputEvent::KeyUp(_) => is_own_window(unsafe { GetForegroundWindow() }),
+                InputEvent::Move { .. } => false,
+            };
+            if ours {
+                continue;
+            }
+            super::replay::record(event, time_ms as u64);
+            // The fetch runs on its own thread
+            let _ = super::feed(&mut recognizer, event, time_ms as u64, &sender);
+        }
+    }
+
+    unsafe extern "system" fn mouse_proc(n_code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+        if n_code >= 0 {
+            let info = *(l_param.0 as *const MSLLHOOKSTRUCT);
+            // Our own input (the Ctrl+C probe, pastes) is not the user's
+            if info.flags & LLMHF_INJECTED == 0 {
+                let (x, y) = (info.pt.x, info.pt.y);
+                let event = match w_param.0 as u32 {
+                    WM_LBUTTONDOWN => Some(InputEvent::ButtonDown { x, y }),
+                    WM_LBUTTONUP => Some(InputEvent::ButtonUp { x, y }),
+                    WM_MOUSEMOVE => Some(InputEvent::Move { x, y }),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    feed(event, info.time);
                 }
-                _ => {}
             }
         }
         CallNextHookEx(None, n_code, w_param, l_param)
     }
-}
 
-#[cfg(not(target_os = "windows"))]
-mod dummy_impl {
-    use super::*;
-    use std::thread;
-    use std::time::Duration;
+    unsafe extern "system" fn keyboard_proc(n_code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+        if n_code >= 0 {
+            let info = *(l_param.0 as *const KBDLLHOOKSTRUCT);
+            if !info.flags.contains(LLKHF_INJECTED) {
+                let key = key_from_vk(VIRTUAL_KEY(info.vkCode as u16));
+                let event = match w_param.0 as u32 {
+                    WM_KEYDOWN | WM_SYSKEYDOWN => Some(InputEvent::KeyDown(key)),
+                    WM_KEYUP | WM_SYSKEYUP => Some(InputEvent::KeyUp(key)),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    feed(event, info.time);
+                }
+            }
+        }
+        CallNextHookEx(None, n_code, w_param, l_param)
+    }
 
-    pub fn start_global_hook(sender: Sender<AppEvent>) {
-        println!("Starting dummy hook for simulation...");
-        thread::spawn(move || {
-            // Simulate a selection event after 3 seconds
-            thread::sleep(Duration::from_secs(3));
-            let _ = sender.send(AppEvent::SelectionDetected {
-                text: "Simulation Text".to_string(),
-                position: (200, 200),
-            });
-            println!("Simulated selection event sent!");
-        });
+    fn is_own_window(hwnd: HWND) -> bool {
+        let mut pid = 0u32;
+        unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+        pid == std::process::id()
     }
 
-    pub fn stop_global_hook() {}
+    fn key_from_vk(vk: VIRTUAL_KEY) -> Key {
+        if [VK_SHIFT, VK_LSHIFT, VK_RSHIFT].contains(&vk) {
+            Key::Shift
+        } else if [VK_CONTROL, VK_LCONTROL, VK_RCONTROL].contains(&vk) {
+            Key::Control
+        } else if [VK_LEFT, VK_RIGHT, VK_UP, VK_DOWN, VK_HOME, VK_END, VK_PRIOR, VK_NEXT].contains(&vk) {
+            Key::Navigation
+        } else if vk == VK_A {
+            Key::A
+        } else {
+            Key::Other
+        }
+    }
 }
 
+#[cfg(all(unix, not(target_os = "macos")))]
+pub mod x11;
+
 #[cfg(target_os = "windows")]
-pub use windows_impl::*;
+pub use windows_impl::MouseHookSource;
 
-#[cfg(not(target_os = "windows"))]
-pub use dummy_impl::*;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use x11::X11SelectionSource;
+
+// Run one input event through `recognizer` and act on the result. Before anything that can
+// start a gesture, the thresholds are re-read for the foreground app and the monitor under
+// the pointer. Returns the selection fetch, if one was started.
+pub fn feed(
+    recognizer: &mut GestureRecognizer,
+    event: InputEvent,
+    at_ms: u64,
+    sender: &Sender<AppEvent>,
+) -> Option<thread::JoinHandle<()>> {
+    let position = match event {
+        InputEvent::ButtonDown { x, y } => Some((x, y)),
+        InputEvent::KeyDown(Key::Shift | Key::Control) => Some(recognizer.pointer()),
+        _ => None,
+    };
+    if let Some(position) = position {
+        let app = crate::automation::foreground_app();
+        let scale = crate::automation::dpi_scale_at(position);
+        recognizer.set_thresholds(SELECTION.lock().unwrap().thresholds(app.as_deref()).scaled(scale));
+    }
+    let output = recognizer.feed(event, at_ms)?;
+    dispatch(output, sender, recognizer.thresholds().settle_delay())
+}
+
+// Hide the toolbar, or give the app `settle` to update its selection after the input that
+// made it, then fetch the text off-thread and show it there
+fn dispatch(output: GestureOutput, sender: &Sender<AppEvent>, settle: Duration) -> Option<thread::JoinHandle<()>> {
+    match output {
+        GestureOutput::Cleared => {
+            let _ = sender.send(AppEvent::SelectionCleared);
+            None
+        }
+        GestureOutput::Selected { gesture, position } => {
+            log::debug!("{:?} selection at {:?}", gesture, position);
+            let sender = sender.clone();
+            Some(thread::spawn(move || {
+                thread::sleep(settle);
+                // Checked before the Ctrl+C probe, which must not reach a disabled app
+                let app = crate::automation::foreground_info();
+                if !crate::profiles::is_enabled(&app) {
+                    return;
+                }
+                if let Some(text) = crate::automation::get_selected_text() {
+                    if !text.trim().is_empty() {
+                        let _ = sender.send(AppEvent::SelectionDetected { text, position, app });
+                    }
+                }
+            }))
+        }
+    }
+}
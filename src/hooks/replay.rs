@@ -0,0 +1,163 @@
+// Recorded input for tuning the gesture thresholds without regressions.
+//
+// With POPWIN_RECORD_GESTURES=<file> set, the Windows hook appends every input event it sees
+// to <file>, one JSON object per line: {"at_ms":1234,"event":{"ButtonDown":{"x":10,"y":20}}}.
+// A recording plus the gestures it should produce makes a case (a .json file):
+//
+//   {
+//     "description": "double-click on a word",
+//     "app": "WINWORD.EXE",
+//     "scale": 1.5,
+//     "events": [{"at_ms": 0, "event": {"ButtonDown": {"x": 10, "y": 20}}}, ...],
+//     "expect": ["Cleared", "DoubleClick"]
+//   }
+//
+// `app` (which [[selection.apps]] overrides apply) and `scale` (monitor scaling, 1.0 = 96 DPI)
+// are optional. `popwin --replay-gestures <file or dir>` runs every case through the recognizer
+// with the current [selection] config and lists the ones whose output differs; a raw .jsonl
+// recording just has its recognized gestures printed, which is how a case's `expect` is written.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use super::gesture::{GestureOutput, GestureRecognizer, InputEvent, SelectionConfig};
+
+const RECORD_ENV: &str = "POPWIN_RECORD_GESTURES";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub at_ms: u64,
+    pub event: InputEvent,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    #[serde(default)]
+    pub description: String,
+    pub app: Option<String>,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    pub events: Vec<RecordedEvent>,
+    // "Cleared" or a gesture name ("Drag", "DoubleClick", ...), in order
+    pub expect: Vec<String>,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))] // only the Windows hook records
+static RECORDER: Lazy<Mutex<Option<File>>> = Lazy::new(|| Mutex::new(open_recording()));
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn open_recording() -> Option<File> {
+    let path = PathBuf::from(std::env::var_os(RECORD_ENV)?);
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => {
+            log::info!("Recording gesture input to {}", path.display());
+            Some(file)
+        }
+        Err(e) => {
+            log::warn!("Cannot record gesture input to {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// Append `event` to the recording, if POPWIN_RECORD_GESTURES asked for one
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn record(event: InputEvent, at_ms: u64) {
+    let mut recorder = RECORDER.lock().unwrap();
+    let Some(file) = recorder.as_mut() else { return };
+    let line = serde_json::to_string(&RecordedEvent { at_ms, event }).unwrap_or_default();
+    if let Err(e) = writeln!(file, "{}", line) {
+        log::warn!("Gesture recording stopped: {}", e);
+        *recorder = None;
+    }
+}
+
+// What the recognizer makes of `events` with these thresholds, as "Cleared" / gesture names
+pub fn replay(config: &SelectionConfig, app: Option<&str>, scale: f32, events: &[RecordedEvent]) -> Vec<String> {
+    let mut recognizer = GestureRecognizer::new();
+    recognizer.set_thresholds(config.thresholds(app).scaled(scale));
+    events
+        .iter()
+        .filter_map(|recorded| recognizer.feed(recorded.event, recorded.at_ms))
+        .map(|output| match output {
+            GestureOutput::Cleared => "Cleared".to_string(),
+            GestureOutput::Selected { gesture, .. } => format!("{:?}", gesture),
+        })
+        .collect()
+}
+
+// Replay a case, a raw recording, or every one of them in a directory, printing the results.
+// Returns whether all cases produced what they expect.
+pub fn run(path: &Path, config: &SelectionConfig) -> Result<bool, String> {
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("json") | Some("jsonl")))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+    for file in &files {
+        let content = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+        if file.extension().is_some_and(|e| e == "jsonl") {
+            let events = content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<RecordedEvent>, _>>()
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            println!("{}: {:?}", file.display(), replay(config, None, 1.0, &events));
+            continue;
+        }
+        let case: Case = serde_json::from_str(&content).map_err(|e| format!("{}: {}", file.display(), e))?;
+        let got = replay(config, case.app.as_deref(), case.scale, &case.events);
+        if got == case.expect {
+            passed += 1;
+            println!("ok    {} {}", file.display(), case.description);
+        } else {
+            failed += 1;
+            println!("FAIL  {} {}", file.display(), case.description);
+            println!("      expected {:?}", case.expect);
+            println!("      got      {:?}", got);
+        }
+    }
+    println!("{} passed, {} failed", passed, failed);
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_cases_pass_with_default_thresholds() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("gestures");
+        let mut cases = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+            let case: Case = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let got = replay(&SelectionConfig::default(), case.app.as_deref(), case.scale, &case.events);
+            assert_eq!(got, case.expect, "{} ({})", path.display(), case.description);
+            cases += 1;
+        }
+        assert!(cases > 0, "no cases in {}", dir.display());
+    }
+}
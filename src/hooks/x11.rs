@@ -0,0 +1,183 @@
+// X11 selection backend: PRIMARY ownership changes (XFixes) and left button presses/releases
+// (XInput2 raw events, which reach us without grabbing the pointer).
+//
+// A mouse selection is: button down, the app claims PRIMARY, button up. Some apps only claim
+// PRIMARY on release, so a claim shortly after the release counts too. The text is then
+// requested from the owner and reported at the pointer position. Works under Xvfb as well.
+
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use x11rb::connection::Connection;
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xinput::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, CreateWindowAux, Window, WindowClass};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::platform::SelectionSource;
+use crate::AppEvent;
+
+type X11Result<T> = Result<T, Box<dyn Error>>;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        UTF8_STRING,
+        INCR,
+        POPWIN_SELECTION,
+    }
+}
+
+const LEFT_BUTTON: u32 = 1;
+const LATE_CLAIM_WINDOW: Duration = Duration::from_millis(150);
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+// In 32-bit units (4 MB); owners send anything larger incrementally (INCR), which we don't read
+const MAX_READ_LEN: u32 = 1 << 20;
+
+pub struct X11SelectionSource;
+
+impl SelectionSource for X11SelectionSource {
+    // Fails when there is no X server or it lacks XFixes / XInput 2
+    fn start(&mut self, sender: Sender<AppEvent>) -> Result<(), String> {
+        start_selection_listener(sender).map_err(|e| e.to_string())?;
+        log::info!("Listening for X11 PRIMARY selections");
+        Ok(())
+    }
+}
+
+fn start_selection_listener(sender: Sender<AppEvent>) -> X11Result<()> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+
+    conn.xfixes_query_version(5, 0)?.reply()?;
+    let xi = conn.xinput_xi_query_version(2, 2)?.reply()?;
+    if xi.major_version < 2 {
+        return Err(format!("XInput {}.{} is too old", xi.major_version, xi.minor_version).into());
+    }
+    conn.xfixes_select_selection_input(root, AtomEnum::PRIMARY.into(), SelectionEventMask::SET_SELECTION_OWNER)?;
+    conn.xinput_xi_select_events(
+        root,
+        &[xinput::EventMask {
+            deviceid: xinput::Device::ALL_MASTER.into(),
+            mask: vec![xinput::XIEventMask::RAW_BUTTON_PRESS | xinput::XIEventMask::RAW_BUTTON_RELEASE],
+        }],
+    )?;
+    conn.flush()?;
+
+    thread::spawn(move || {
+        if let Err(e) = listen(&conn, root, &sender) {
+            log::error!("X11 selection listener stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+fn listen(conn: &RustConnection, root: Window, sender: &Sender<AppEvent>) -> X11Result<()> {
+    let mut pressed = false;
+    let mut claimed = false;
+    let mut released: Option<(Instant, (i32, i32))> = None;
+    loop {
+        match conn.wait_for_event()? {
+            Event::XinputRawButtonPress(e) if e.detail == LEFT_BUTTON => {
+                pressed = true;
+                claimed = false;
+                released = None;
+                let _ = sender.send(AppEvent::SelectionCleared);
+            }
+            Event::XinputRawButtonRelease(e) if e.detail == LEFT_BUTTON => {
+                pressed = false;
+                // Raw events carry no coordinates
+                let pointer = conn.query_pointer(root)?.reply()?;
+                let position = (pointer.root_x as i32, pointer.root_y as i32);
+                if claimed {
+                    report_selection(sender, position);
+                } else {
+                    released = Some((Instant::now(), position));
+                }
+            }
+            Event::XfixesSelectionNotify(_) => {
+                if pressed {
+                    claimed = true;
+                } else if let Some((at, position)) = released.take() {
+                    if at.elapsed() <= LATE_CLAIM_WINDOW {
+                        report_selection(sender, position);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Reading needs a round trip through the owner, so it runs off the listener thread
+fn report_selection(sender: &Sender<AppEvent>, position: (i32, i32)) {
+    let sender = sender.clone();
+    thread::spawn(move || {
+        let app = crate::automation::foreground_info();
+        if !crate::profiles::is_enabled(&app) {
+            return;
+        }
+        match read_primary() {
+            Ok(Some(text)) if !text.trim().is_empty() => {
+                let _ = sender.send(AppEvent::SelectionDetected { text, position, app });
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Cannot read the X11 selection: {}", e),
+        }
+    });
+}
+
+// Ask the PRIMARY owner for the text, as UTF-8 if it can, else Latin-1
+pub fn read_primary() -> X11Result<Option<String>> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let atoms = Atoms::new(&conn)?.reply()?;
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )?;
+
+    for target in [atoms.UTF8_STRING, AtomEnum::STRING.into()] {
+        conn.convert_selection(window, AtomEnum::PRIMARY.into(), target, atoms.POPWIN_SELECTION, x11rb::CURRENT_TIME)?;
+        conn.flush()?;
+
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let notify = loop {
+            match conn.poll_for_event()? {
+                Some(Event::SelectionNotify(e)) if e.requestor == window => break e,
+                Some(_) => {}
+                None if Instant::now() >= deadline => return Err("the selection owner did not answer".into()),
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        };
+        // The owner can't convert to this target
+        if notify.property == x11rb::NONE {
+            continue;
+        }
+        let reply = conn
+            .get_property(true, window, atoms.POPWIN_SELECTION, AtomEnum::ANY, 0, MAX_READ_LEN)?
+            .reply()?;
+        if reply.type_ == atoms.INCR {
+            return Err("the selection is too large".into());
+        }
+        let text = if target == atoms.UTF8_STRING {
+            String::from_utf8_lossy(&reply.value).into_owned()
+        } else {
+            reply.value.iter().map(|&b| b as char).collect()
+        };
+        return Ok(Some(text));
+    }
+    Ok(None)
+}
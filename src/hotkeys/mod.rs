@@ -0,0 +1,314 @@
+// Global hotkeys that summon the toolbar without a selection gesture: one for whatever is
+// selected in the focused app (fetched like after a mouse selection), one for the clipboard.
+// The toolbar opens at the text caret when the app has one, else at the mouse cursor.
+//
+//   [hotkeys]
+//   selection = "Ctrl+Alt+Space"
+//   clipboard = "Ctrl+Alt+V"      # "" turns a hotkey off
+//
+// Registered with RegisterHotKey on Windows and as a key grab on the X11 root window.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crossbeam_channel::Sender;
+use serde::Deserialize;
+
+use crate::config::LoadIssue;
+use crate::AppEvent;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HotkeyConfig {
+    pub selection: String,
+    pub clipboard: String,
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            selection: "Ctrl+Alt+Space".to_string(),
+            clipboard: "Ctrl+Alt+V".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Selection,
+    Clipboard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyKey {
+    // A-Z (uppercase) or 0-9
+    Char(char),
+    Function(u8),
+    Space,
+    Insert,
+    Pause,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub super_key: bool,
+    pub key: HotkeyKey,
+}
+
+// "Ctrl+Alt+Space", "ctrl + shift + F9", "Win+Insert", ...
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last().ok_or("empty hotkey")?;
+        let mut hotkey = Hotkey { ctrl: false, alt: false, shift: false, super_key: false, key: HotkeyKey::Space };
+        for modifier in modifiers {
+            let held = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut hotkey.ctrl,
+                "alt" => &mut hotkey.alt,
+                "shift" => &mut hotkey.shift,
+                "win" | "super" | "meta" => &mut hotkey.super_key,
+                other => return Err(format!("unknown modifier `{}` in `{}`", other, s)),
+            };
+            if std::mem::replace(held, true) {
+                return Err(format!("`{}` is given twice in `{}`", modifier, s));
+            }
+        }
+        if key.is_empty() {
+            return Err(format!("missing key in `{}`", s));
+        }
+        let upper = key.to_ascii_uppercase();
+        hotkey.key = match upper.as_str() {
+            "SPACE" => HotkeyKey::Space,
+            "INSERT" | "INS" => HotkeyKey::Insert,
+            "PAUSE" => HotkeyKey::Pause,
+            _ if upper.len() == 1 && upper.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                HotkeyKey::Char(upper.chars().next().unwrap_or_default())
+            }
+            _ => match upper.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=24) => HotkeyKey::Function(n),
+                _ => return Err(format!("unknown key `{}` in `{}`", key, s)),
+            },
+        };
+        // Without a modifier, typing the key would summon the toolbar
+        let modified = hotkey.ctrl || hotkey.alt || hotkey.super_key;
+        if !modified && !matches!(hotkey.key, HotkeyKey::Function(_) | HotkeyKey::Pause) {
+            return Err(format!("`{}` needs Ctrl, Alt or Win", s));
+        }
+        Ok(hotkey)
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.ctrl, "Ctrl+"), (self.alt, "Alt+"), (self.shift, "Shift+"), (self.super_key, "Win+")] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        match self.key {
+            HotkeyKey::Char(c) => write!(f, "{}", c),
+            HotkeyKey::Function(n) => write!(f, "F{}", n),
+            HotkeyKey::Space => f.write_str("Space"),
+            HotkeyKey::Insert => f.write_str("Insert"),
+            HotkeyKey::Pause => f.write_str("Pause"),
+        }
+    }
+}
+
+// Register the configured hotkeys. Unparsable ones are returned as config problems; ones
+// another app already owns are only logged.
+pub fn start(config: &HotkeyConfig, sender: &Sender<AppEvent>) -> Vec<LoadIssue> {
+    let mut issues = Vec::new();
+    let mut bindings = Vec::new();
+    for (action, spec) in [(HotkeyAction::Selection, &config.selection), (HotkeyAction::Clipboard, &config.clipboard)] {
+        if spec.trim().is_empty() {
+            continue;
+        }
+        match spec.parse::<Hotkey>() {
+            Ok(hotkey) => bindings.push((action, hotkey)),
+            Err(e) => issues.push(LoadIssue::new(crate::config::config_path(), format!("[hotkeys] {}", e))),
+        }
+    }
+    if bindings.is_empty() {
+        return issues;
+    }
+
+    #[cfg(target_os = "windows")]
+    windows_impl::listen(bindings, sender.clone());
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if let Err(e) = x11::listen(bindings, sender.clone()) {
+        log::info!("No global hotkeys on this desktop ({})", e);
+    }
+
+    #[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos")))))]
+    let _ = (bindings, sender);
+
+    issues
+}
+
+// Fetch the text for `action` and open the toolbar on it. On Windows this runs after the
+// hotkey's modifiers are up, so the Ctrl+C probe isn't sent as Ctrl+Alt+C. X11 has the
+// selection in PRIMARY and gets no probe, which would interrupt the program in a terminal.
+fn summon(action: HotkeyAction, cursor: (i32, i32), sender: &Sender<AppEvent>) {
+    let app = crate::automation::foreground_info();
+    if !crate::profiles::is_enabled(&app) {
+        return;
+    }
+    let text = match action {
+        HotkeyAction::Selection => selected_text(),
+        HotkeyAction::Clipboard => crate::platform::with_clipboard(|clipboard| clipboard.text()),
+    };
+    let Some(text) = text.filter(|text| !text.trim().is_empty()) else {
+        log::info!("{:?} hotkey: no text to show", action);
+        return;
+    };
+    let position = crate::automation::caret_position().unwrap_or(cursor);
+    let _ = sender.send(AppEvent::SelectionDetected { text, position, app });
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn selected_text() -> Option<String> {
+    crate::hooks::x11::read_primary().unwrap_or_else(|e| {
+        log::warn!("Cannot read the X11 selection: {}", e);
+        None
+    })
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn selected_text() -> Option<String> {
+    crate::automation::get_selected_text()
+}
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use super::{summon, Hotkey, HotkeyAction, HotkeyKey};
+    use crate::AppEvent;
+    use crossbeam_channel::Sender;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use windows::Win32::Foundation::{HWND, POINT};
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        GetAsyncKeyState, RegisterHotKey, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+        VIRTUAL_KEY, VK_CONTROL, VK_INSERT, VK_LWIN, VK_MENU, VK_PAUSE, VK_RWIN, VK_SHIFT, VK_SPACE,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetCursorPos, GetMessageW, MSG, WM_HOTKEY};
+
+    const RELEASE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    pub fn listen(bindings: Vec<(HotkeyAction, Hotkey)>, sender: Sender<AppEvent>) {
+        thread::spawn(move || unsafe {
+            // WM_HOTKEY goes to the thread that registered the hotkey
+            for (id, (action, hotkey)) in bindings.iter().enumerate() {
+                match RegisterHotKey(HWND::default(), id as i32, modifiers(hotkey), virtual_key(hotkey.key).0 as u32) {
+                    Ok(()) => log::info!("{:?} hotkey: {}", action, hotkey),
+                    Err(e) => log::warn!("Cannot register the {} hotkey (taken by another app?): {}", hotkey, e),
+                }
+            }
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, HWND::default(), 0, 0).into() {
+                if msg.message != WM_HOTKEY {
+                    continue;
+                }
+                if let Some(&(action, _)) = bindings.get(msg.wParam.0) {
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        wait_for_modifiers_released();
+                        let mut cursor = POINT::default();
+                        let _ = GetCursorPos(&mut cursor);
+                        summon(action, (cursor.x, cursor.y), &sender);
+                    });
+                }
+            }
+        });
+    }
+
+    fn modifiers(hotkey: &Hotkey) -> HOT_KEY_MODIFIERS {
+        let mut modifiers = MOD_NOREPEAT;
+        for (held, modifier) in
+            [(hotkey.ctrl, MOD_CONTROL), (hotkey.alt, MOD_ALT), (hotkey.shift, MOD_SHIFT), (hotkey.super_key, MOD_WIN)]
+        {
+            if held {
+                modifiers = modifiers | modifier;
+            }
+        }
+        modifiers
+    }
+
+    fn virtual_key(key: HotkeyKey) -> VIRTUAL_KEY {
+        match key {
+            // Virtual-key codes of letters and digits are their ASCII codes
+            HotkeyKey::Char(c) => VIRTUAL_KEY(c as u16),
+            HotkeyKey::Function(n) => VIRTUAL_KEY(0x70 + n as u16 - 1),
+            HotkeyKey::Space => VK_SPACE,
+            HotkeyKey::Insert => VK_INSERT,
+            HotkeyKey::Pause => VK_PAUSE,
+        }
+    }
+
+    fn wait_for_modifiers_released() {
+        let deadline = Instant::now() + RELEASE_TIMEOUT;
+        let held = || unsafe {
+            [VK_CONTROL, VK_MENU, VK_SHIFT, VK_LWIN, VK_RWIN].iter().any(|vk| GetAsyncKeyState(vk.0 as i32) < 0)
+        };
+        while held() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Hotkey, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn modifiers_in_any_order_and_case() {
+        let expected = Hotkey { ctrl: true, alt: false, shift: true, super_key: false, key: HotkeyKey::Function(9) };
+        assert_eq!(parse("Ctrl+Shift+F9"), Ok(expected));
+        assert_eq!(parse("shift + CONTROL + f9"), Ok(expected));
+        assert_eq!(parse("Meta+Alt+space").unwrap().to_string(), "Alt+Win+Space");
+        assert_eq!(parse("super+q").unwrap().key, HotkeyKey::Char('Q'));
+        assert_eq!(parse("Win+Ins").unwrap().key, HotkeyKey::Insert);
+    }
+
+    #[test]
+    fn plain_keys_need_a_modifier_unless_they_are_never_typed() {
+        assert_eq!(parse("F12").unwrap().to_string(), "F12");
+        assert_eq!(parse("Pause").unwrap().to_string(), "Pause");
+        assert!(parse("Space").is_err());
+        assert!(parse("Shift+A").unwrap_err().contains("needs Ctrl, Alt or Win"));
+    }
+
+    #[test]
+    fn unknown_keys_and_modifiers_are_rejected() {
+        assert!(parse("Ctrl+Enter").unwrap_err().contains("unknown key `Enter`"));
+        assert!(parse("Ctrl+F25").unwrap_err().contains("unknown key `F25`"));
+        assert!(parse("Ctrl+AB").unwrap_err().contains("unknown key `AB`"));
+        assert!(parse("Hyper+A").unwrap_err().contains("unknown modifier `hyper`"));
+    }
+
+    #[test]
+    fn a_missing_key_is_rejected() {
+        assert!(parse("").unwrap_err().contains("missing key"));
+        assert!(parse("Ctrl+Alt+").unwrap_err().contains("missing key"));
+    }
+
+    #[test]
+    fn duplicate_modifiers_are_rejected() {
+        assert!(parse("Ctrl+Ctrl+A").unwrap_err().contains("given twice"));
+        assert!(parse("Control+Alt+ctrl+A").unwrap_err().contains("`ctrl` is given twice"));
+        assert!(parse("Win+Super+A").is_err());
+    }
+}
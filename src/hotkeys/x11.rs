@@ -0,0 +1,116 @@
+// Hotkeys on X11: passive key grabs on the root window. A grab matches the modifier state
+// exactly, so each hotkey is grabbed once per Caps Lock / Num Lock combination.
+
+use std::error::Error;
+use std::thread;
+
+use crossbeam_channel::Sender;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt as _, GrabMode, Keycode, ModMask, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use super::{summon, Hotkey, HotkeyAction, HotkeyKey};
+use crate::AppEvent;
+
+type X11Result<T> = Result<T, Box<dyn Error>>;
+
+const XK_F1: u32 = 0xffbe;
+const XK_INSERT: u32 = 0xff63;
+const XK_PAUSE: u32 = 0xff13;
+
+// Fails when there is no X server; a hotkey another client already grabbed is only logged
+pub fn listen(bindings: Vec<(HotkeyAction, Hotkey)>, sender: Sender<AppEvent>) -> X11Result<()> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+
+    // Mod2 is Num Lock on practically every layout
+    let (caps, num) = (u16::from(ModMask::LOCK), u16::from(ModMask::M2));
+    let mut grabs = Vec::new();
+    for (action, hotkey) in bindings {
+        let Some(keycode) = keycode(&conn, keysym(hotkey.key))? else {
+            log::warn!("Cannot register the {} hotkey: no key produces it", hotkey);
+            continue;
+        };
+        let modifiers = modifiers(&hotkey);
+        let grabbed = [0, caps, num, caps | num].iter().try_for_each(|&lock| {
+            conn.grab_key(true, root, ModMask::from(modifiers | lock), keycode, GrabMode::ASYNC, GrabMode::ASYNC)?
+                .check()
+                .map_err(Box::<dyn Error>::from)
+        });
+        match grabbed {
+            Ok(()) => {
+                log::info!("{:?} hotkey: {}", action, hotkey);
+                grabs.push((action, keycode, modifiers));
+            }
+            Err(e) => log::warn!("Cannot register the {} hotkey (taken by another app?): {}", hotkey, e),
+        }
+    }
+    if grabs.is_empty() {
+        return Err("no hotkey could be grabbed".into());
+    }
+
+    thread::spawn(move || loop {
+        let event = match conn.wait_for_event() {
+            Ok(event) => event,
+            Err(e) => {
+                log::error!("X11 hotkey listener stopped: {}", e);
+                return;
+            }
+        };
+        let Event::KeyPress(press) = event else { continue };
+        // Ignore the lock keys and held mouse buttons
+        let state = u16::from(press.state) & HOTKEY_MODIFIERS;
+        if let Some(&(action, _, _)) = grabs.iter().find(|(_, keycode, mods)| *keycode == press.detail && *mods == state) {
+            let sender = sender.clone();
+            thread::spawn(move || match pointer(root) {
+                Ok(cursor) => summon(action, cursor, &sender),
+                Err(e) => log::warn!("{:?} hotkey: {}", action, e),
+            });
+        }
+    });
+    Ok(())
+}
+
+const HOTKEY_MODIFIERS: u16 = 0x1 | 0x4 | 0x8 | 0x40; // Shift, Control, Mod1 (Alt), Mod4 (Super)
+
+fn modifiers(hotkey: &Hotkey) -> u16 {
+    [
+        (hotkey.ctrl, ModMask::CONTROL),
+        (hotkey.alt, ModMask::M1),
+        (hotkey.shift, ModMask::SHIFT),
+        (hotkey.super_key, ModMask::M4),
+    ]
+    .iter()
+    .filter(|(held, _)| *held)
+    .fold(0, |mask, (_, modifier)| mask | u16::from(*modifier))
+}
+
+fn keysym(key: HotkeyKey) -> u32 {
+    match key {
+        // Latin keysyms are the lowercase ASCII codes
+        HotkeyKey::Char(c) => c.to_ascii_lowercase() as u32,
+        HotkeyKey::Function(n) => XK_F1 + n as u32 - 1,
+        HotkeyKey::Space => ' ' as u32,
+        HotkeyKey::Insert => XK_INSERT,
+        HotkeyKey::Pause => XK_PAUSE,
+    }
+}
+
+fn keycode(conn: &RustConnection, keysym: u32) -> X11Result<Option<Keycode>> {
+    let (min, max) = (conn.setup().min_keycode, conn.setup().max_keycode);
+    let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+    let per_keycode = (mapping.keysyms_per_keycode as usize).max(1);
+    Ok(mapping
+        .keysyms
+        .chunks(per_keycode)
+        .position(|keysyms| keysyms.contains(&keysym))
+        .map(|index| min + index as u8))
+}
+
+// Runs on its own connection; the listener's is blocked waiting for events
+fn pointer(root: Window) -> X11Result<(i32, i32)> {
+    let (conn, _) = x11rb::connect(None)?;
+    let pointer = conn.query_pointer(root)?.reply()?;
+    Ok((pointer.root_x as i32, pointer.root_y as i32))
+}
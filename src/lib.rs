@@ -0,0 +1,69 @@
+// PopWin's pipeline as a library, so integration tests can drive it on the mock platform;
+// main.rs only starts it.
+
+pub mod hooks;
+pub mod automation;
+pub mod actions;
+pub mod app;
+pub mod config;
+pub mod history;
+pub mod hotkeys;
+pub mod llm;
+pub mod platform;
+pub mod profiles;
+pub mod translation;
+
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    SelectionDetected {
+        text: String,
+        position: (i32, i32),
+        // The app the text was selected in, for the [[apps]] rules
+        app: automation::AppInfo,
+    },
+    SelectionCleared,
+    TranslationReceived {
+        request_id: actions::RequestId,
+        translation: translation::Translation,
+    },
+    TranslationFailed {
+        request_id: actions::RequestId,
+        error: translation::TranslationError,
+    },
+    // Back-translation check: original, translation and the translation translated back
+    RoundTripReceived {
+        request_id: actions::RequestId,
+        round_trip: translation::RoundTrip,
+    },
+    // A long selection is translated in chunks; sent after each one completes
+    TranslationProgress {
+        request_id: actions::RequestId,
+        done: usize,
+        total: usize,
+    },
+    // Comparison mode: the provider list first, then one result per provider
+    ComparisonStarted {
+        request_id: actions::RequestId,
+        providers: Vec<String>,
+    },
+    ComparisonResult {
+        request_id: actions::RequestId,
+        provider: String,
+        result: translation::TranslationResult,
+    },
+    // Incremental text of a streaming action, followed by one StreamFinished
+    StreamChunk {
+        request_id: actions::RequestId,
+        text: String,
+    },
+    StreamFinished {
+        request_id: actions::RequestId,
+        result: Result<(), String>,
+    },
+    ActionOutput {
+        request_id: actions::RequestId,
+        action_id: String,
+        mode: actions::OutputMode,
+        result: Result<String, String>,
+    },
+}
@@ -0,0 +1,195 @@
+// Streaming chat completions against any OpenAI-compatible endpoint (OpenAI, Ollama, llama.cpp)
+
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::actions::CancelToken;
+
+mod sse;
+
+pub use sse::SseParser;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    // LLM actions are only offered once a model is configured
+    pub model: Option<String>,
+    // Whole-response timeout; streams of long answers need more than a translation does
+    pub timeout_secs: u64,
+    // Prompt ids that get their own toolbar button; the rest are in the ✨ picker
+    pub toolbar: Vec<String>,
+    // Extra prompts; a prompt with a built-in id replaces it
+    pub prompts: Vec<PromptTemplate>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:11434/v1".to_string(),
+            api_key: None,
+            model: None,
+            timeout_secs: 120,
+            toolbar: Vec::new(),
+            prompts: Vec::new(),
+        }
+    }
+}
+
+//   [[llm.prompts]]
+//   id = "polite"
+//   name = "Polite"
+//   icon = "🙇"
+//   system = "You are a careful business writer."
+//   template = "Rewrite this as a polite business email:\n\n{text}"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub icon: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    // `{text}` is replaced by the selection; without it the selection is appended
+    pub template: String,
+}
+
+impl PromptTemplate {
+    fn builtin(id: &str, name: &str, icon: &str, template: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            icon: icon.to_string(),
+            system: None,
+            template: template.to_string(),
+        }
+    }
+
+    pub fn render(&self, text: &str) -> String {
+        if self.template.contains("{text}") {
+            self.template.replace("{text}", text)
+        } else {
+            format!("{}\n\n{}", self.template, text)
+        }
+    }
+}
+
+pub fn builtin_prompts() -> Vec<PromptTemplate> {
+    vec![
+        PromptTemplate::builtin(
+            "summarize",
+            "Summarize",
+            "📝",
+            "Summarize the following text concisely, in the same language as the text:\n\n{text}",
+        ),
+        PromptTemplate::builtin(
+            "explain",
+            "Explain",
+            "💡",
+            "Explain the following text in simple terms, in the same language as the text:\n\n{text}",
+        ),
+        PromptTemplate::builtin(
+            "rewrite",
+            "Rewrite",
+            "✍️",
+            "Rewrite the following text to read clearer and more naturally. Keep its language. \
+             Reply with the rewritten text only:\n\n{text}",
+        ),
+        PromptTemplate::builtin(
+            "grammar",
+            "Fix grammar",
+            "✔️",
+            "Fix grammar and spelling in the following text without changing its meaning or language. \
+             Reply with the corrected text only:\n\n{text}",
+        ),
+    ]
+}
+
+pub struct LlmClient {
+    client: reqwest::blocking::Client,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl LlmClient {
+    pub fn new(config: &LlmConfig, model: &str) -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            url: format!("{}/chat/completions", config.base_url.trim_end_matches('/')),
+            api_key: config
+                .api_key
+                .clone()
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+                .filter(|k| !k.trim().is_empty()),
+            model: model.to_string(),
+        }
+    }
+
+    // Stream a completion, calling `on_delta` for each token chunk as it arrives.
+    // Returns Ok(false) if cancelled part-way.
+    pub fn stream(
+        &self,
+        system: Option<&str>,
+        prompt: &str,
+        cancel: &CancelToken,
+        mut on_delta: impl FnMut(&str),
+    ) -> Result<bool, String> {
+        let mut messages = Vec::new();
+        if let Some(system) = system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(json!({ "role": "user", "content": prompt }));
+        let body = json!({ "model": self.model, "stream": true, "messages": messages });
+
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let resp = request.send().map_err(|e| e.to_string())?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body: String = resp.text().unwrap_or_default().chars().take(200).collect();
+            return Err(format!("HTTP {}: {}", status, body.trim()));
+        }
+
+        let mut parser = SseParser::new();
+        let mut reader = BufReader::new(resp);
+        let mut line = String::new();
+        loop {
+            if cancel.is_cancelled() {
+                return Ok(false);
+            }
+            line.clear();
+            let read = reader.read_line(&mut line).map_err(|e| e.to_string())?;
+            let payload = if read == 0 { parser.flush() } else { parser.push_line(&line) };
+            if let Some(payload) = payload {
+                if payload.trim() == "[DONE]" {
+                    return Ok(true);
+                }
+                let chunk: serde_json::Value =
+                    serde_json::from_str(&payload).map_err(|e| format!("bad stream chunk: {}", e))?;
+                if let Some(message) = chunk["error"]["message"].as_str() {
+                    return Err(message.to_string());
+                }
+                if let Some(delta) = chunk["choices"][0]["delta"]["content"].as_str() {
+                    if !delta.is_empty() {
+                        on_delta(delta);
+                    }
+                }
+            }
+            if read == 0 {
+                return Ok(true);
+            }
+        }
+    }
+}
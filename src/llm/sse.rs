@@ -0,0 +1,88 @@
+// Minimal Server-Sent Events parser: feed it lines, get back the `data` payload of each
+// completed event. Only what OpenAI-style streams use is handled (data, comments, blank-line dispatch).
+#[derive(Debug, Default)]
+pub struct SseParser {
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the event payload when `line` completes an event
+    pub fn push_line(&mut self, line: &str) -> Option<String> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            return self.flush();
+        }
+        if line.starts_with(':') {
+            return None; // comment / keep-alive
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        if field == "data" {
+            self.data.push(value.to_string());
+        }
+        None
+    }
+
+    // Dispatch whatever is buffered; call at end of stream for servers that omit the final blank line
+    pub fn flush(&mut self) -> Option<String> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let payload = self.data.join("\n");
+        self.data.clear();
+        Some(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stream: &str) -> Vec<String> {
+        let mut parser = SseParser::new();
+        let mut events: Vec<String> = stream.split_inclusive('\n').filter_map(|line| parser.push_line(line)).collect();
+        events.extend(parser.flush());
+        events
+    }
+
+    #[test]
+    fn blank_lines_end_events() {
+        assert_eq!(parse("data: {\"a\":1}\n\ndata: {\"a\":2}\n\n"), ["{\"a\":1}", "{\"a\":2}"]);
+    }
+
+    #[test]
+    fn data_lines_of_one_event_are_joined() {
+        assert_eq!(parse("data: first\ndata: second\ndata:third\n\n"), ["first\nsecond\nthird"]);
+    }
+
+    #[test]
+    fn comments_and_other_fields_are_skipped() {
+        let stream = ": keep-alive\n\nevent: message\nid: 7\ndata: hello\nretry: 100\n\n: bye\n";
+        assert_eq!(parse(stream), ["hello"]);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        assert_eq!(parse("data: hello\r\n\r\ndata: world\r\n\r\n"), ["hello", "world"]);
+    }
+
+    #[test]
+    fn last_event_without_a_blank_line_is_flushed() {
+        let mut parser = SseParser::new();
+        assert_eq!(parser.push_line("data: partial\n"), None);
+        assert_eq!(parser.flush().as_deref(), Some("partial"));
+        assert_eq!(parser.flush(), None);
+    }
+
+    #[test]
+    fn done_marker_is_an_ordinary_payload() {
+        // The caller stops on it
+        assert_eq!(parse("data: {\"x\":1}\n\ndata: [DONE]\n\n"), ["{\"x\":1}", "[DONE]"]);
+    }
+}
@@ -1,31 +1,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // Hide console in release
 
-#[cfg(target_os = "windows")]
 use eframe::egui;
-#[cfg(not(target_os = "windows"))]
-use eframe::egui as _; // Keep it if needed for AppEvent but let's check.
-// AppEvent uses deriving Debug, Clone which are standard. 
-// It doesn't use egui types. So egui is only for run_native.
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use log::info;
 
-mod hooks;
-mod automation;
-mod actions;
-mod app;
-
-#[cfg(target_os = "windows")]
-use app::PopWinApp;
-
-#[derive(Debug, Clone)]
-pub enum AppEvent {
-    SelectionDetected {
-        text: String,
-        position: (i32, i32),
-    },
-    SelectionCleared,
-    TranslationReceived(String),
-}
+use popwin::app::PopWinApp;
+use popwin::{actions, config, history, hooks, hotkeys, platform, profiles, translation, AppEvent};
 
 fn main() -> Result<(), eframe::Error> {
     env_logger::init();
@@ -34,146 +14,285 @@ fn main() -> Result<(), eframe::Error> {
     // Channel for communication between hook thread and UI thread
     let (tx, rx): (Sender<AppEvent>, Receiver<AppEvent>) = unbounded();
 
-    // Start global hook in a background thread
-    let tx_clone = tx.clone();
-    hooks::start_global_hook(tx_clone);
-
-    // Windows (Production): Run GUI
-    #[cfg(target_os = "windows")]
-    {
-        let mut viewport_builder = egui::ViewportBuilder::default()
-            .with_decorations(false) // Borderless
-            .with_transparent(true)  // Transparent
-            .with_always_on_top()
-            .with_inner_size([80.0, 120.0]) // Vertical layout
-            .with_position(egui::Pos2::new(100.0, 100.0));
-
-        viewport_builder = viewport_builder.with_taskbar(false).with_always_on_top();
-        
-        let options = eframe::NativeOptions {
-            viewport: viewport_builder,
-            ..Default::default()
-        };
-
-        let tx_options = tx.clone();
-        eframe::run_native(
-            "PopWin",
-            options,
-            Box::new(|cc| Box::new(PopWinApp::new(cc, rx, tx_options))),
-        )
+    let (config, config_issue) = config::Config::load();
+
+    // Tuning aid: replay recorded gestures against the configured thresholds, then exit
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [flag, path] = args.as_slice() {
+        if flag == "--replay-gestures" {
+            let passed = hooks::replay::run(std::path::Path::new(path), &config.selection).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                false
+            });
+            std::process::exit(if passed { 0 } else { 1 });
+        }
+    }
+
+    let history_issue = history::init(&config.history);
+    hooks::configure(&config.selection);
+    profiles::configure(&config.apps);
+
+    // Selections from the desktop's hook/listener, or a scripted demo on the mock platform
+    let mut selection_source = platform::start_selection_source(&tx);
+    let hotkey_issues = hotkeys::start(&config.hotkeys, &tx);
+
+    // Both frontends build their toolbar from the same registry: built-ins first, then packs
+    let (mut registry, builtin_issues) = actions::ActionRegistry::with_builtins(&config);
+    let mut pack_report = actions::load_packs(&config.action_pack_dirs(), &mut registry);
+    let config_issues: Vec<_> = config_issue.into_iter().chain(history_issue).chain(hotkey_issues).chain(builtin_issues).collect();
+    for issue in &config_issues {
+        log::warn!("Config problem: {}", issue);
     }
+    pack_report.issues.splice(0..0, config_issues);
 
-    // macOS/Others (Simulation): TUI Simulation with ANSI codes
     #[cfg(not(target_os = "windows"))]
-    {
-        use std::io::{Write, stdout};
-        use std::thread::sleep;
-        use std::time::Duration;
-
-        // Clear screen and hide cursor
-        print!("\x1b[2J\x1b[?25l"); 
-        
-        println!("\x1b[1;1HPopWin Simulation (macOS TUI Mode)");
-        println!("\x1b[3;1HWaiting for text selection...");
-
-        // Keep track of window state for redraw
-        let mut last_text = String::new();
-        let mut last_pos = (10, 5); // Default sim pos
-
-        loop {
-            if let Ok(event) = rx.recv() {
-                match event {
-                    AppEvent::SelectionDetected { text, position: _ } => {
-                        last_text = text.clone();
-                        // Draw centered window
-                        let x = 10;
-                        let y = 5;
-                        last_pos = (x, y);
-
-                        let text_display = if text.len() > 10 {
-                            format!("{}...", &text[..10])
-                        } else {
-                            format!("{: <13}", text)
-                        };
-
-                        // Simulate Fade-In Animation
-                        for i in 1..=5 {
-                            print!("\x1b[2J"); // Clear
-                            print!("\x1b[1;1HPopWin Simulation (macOS TUI Mode)");
-                            
-                            let frame_color = if i < 3 { 90 } else { 37 }; // Dark gray to White
-                            
-                            // Top border
-                            print!("\x1b[{};{}H\x1b[{}m+-------------------------+", y, x, frame_color);
-                            // Title
-                            print!("\x1b[{};{}H|  \x1b[1mPopWin Toolbar\x1b[0m\x1b[{}m         |", y+1, x, frame_color);
-                            // Separator
-                            print!("\x1b[{};{}H|-------------------------|", y+2, x);
-                            // Buttons
-                            print!("\x1b[{};{}H|  [C] Copy   [V] Paste    |", y+3, x);
-                            print!("\x1b[{};{}H|  [S] Search [E] EN       |", y+4, x);
-                            print!("\x1b[{};{}H|                         |", y+5, x);
-                            // Selected Text
-                            print!("\x1b[{};{}H|  Selected: \x1b[36m{}\x1b[0m\x1b[{}m|", y+6, x, text_display, frame_color); 
-                            // Bottom border
-                            print!("\x1b[{};{}H+-------------------------+\x1b[0m", y+7, x);
-                            
-                            print!("\x1b[{};{}H(Animation Frame: {}/5)", y+13, x, i);
-                            stdout().flush().unwrap();
-                            sleep(Duration::from_millis(150));
-                        }
+    if !toolbar_supported() {
+        return run_simulation(registry, pack_report, rx, tx);
+    }
 
-                        // Interaction simulation: Click EN
-                        sleep(Duration::from_secs(1));
-                        print!("\x1b[{};{}H\x1b[32m> User clicked [EN] (Requesting Translation...)\x1b[0m", y+10, x);
-                        stdout().flush().unwrap();
-                        
-                        // Call async translation
-                        actions::translate_async(&text, tx.clone());
-                    }
-                    AppEvent::TranslationReceived(translation) => {
-                         let (x, y) = last_pos;
-                         let text_display = if last_text.len() > 10 {
-                            format!("{}...", &last_text[..10])
-                        } else {
-                            format!("{: <13}", last_text)
-                        };
-                        let frame_color = 37;
-
-                        // Redraw window with translation result
+    // Windows and Linux/X11: Run GUI
+    // Kept out of the taskbar: egui-winit honours with_taskbar on Windows only, and on X11 sets
+    // the window type, which most (not all) taskbars skip for utility windows
+    let viewport_builder = egui::ViewportBuilder::default()
+        .with_decorations(false) // Borderless
+        .with_transparent(true)  // Transparent
+        .with_always_on_top()
+        .with_inner_size([80.0, 120.0]) // Vertical layout
+        .with_position(egui::Pos2::new(100.0, 100.0))
+        .with_taskbar(false)
+        .with_window_type(egui::X11WindowType::Utility);
+
+    let options = eframe::NativeOptions {
+        viewport: viewport_builder,
+        ..Default::default()
+    };
+
+    let tx_options = tx.clone();
+    let result = eframe::run_native(
+        "PopWin",
+        options,
+        Box::new(|cc| Box::new(PopWinApp::new(cc, registry, pack_report, rx, tx_options))),
+    );
+    selection_source.stop();
+    result
+}
+
+// Without a desktop to draw on (Simulation): TUI Simulation with ANSI codes
+#[cfg(not(target_os = "windows"))]
+fn run_simulation(
+    registry: actions::ActionRegistry,
+    pack_report: actions::PackReport,
+    rx: Receiver<AppEvent>,
+    tx: Sender<AppEvent>,
+) -> Result<(), eframe::Error> {
+    use std::io::{Write, stdout};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    // Clear screen and hide cursor
+    print!("\x1b[2J\x1b[?25l"); 
+    
+    println!("\x1b[1;1HPopWin Simulation (TUI Mode)");
+    println!("\x1b[3;1HWaiting for text selection...");
+    for (i, issue) in pack_report.issues.iter().enumerate() {
+        println!("\x1b[{};1H\x1b[33m! {}\x1b[0m", 4 + i, issue);
+    }
+
+    // Keep track of window state for redraw
+    let mut last_selection = actions::Selection::default();
+    let cancel = actions::CancelToken::new();
+    let mut last_pos = (10, 5); // Default sim pos
+
+    loop {
+        if let Ok(event) = rx.recv() {
+            match event {
+                AppEvent::SelectionDetected { text, position, app } => {
+                    let selection = actions::Selection { app, ..actions::Selection::new(text.clone(), position) };
+                    let button_rows = tui_button_rows(&registry, &selection);
+                    let rows = button_rows.len();
+                    last_selection = selection;
+                    // Draw centered window
+                    let x = 10;
+                    let y = 5;
+                    last_pos = (x, y);
+
+                    let text_display = if text.chars().count() > 10 {
+                        format!("{}...", text.chars().take(10).collect::<String>())
+                    } else {
+                        format!("{: <13}", text)
+                    };
+
+                    // Simulate Fade-In Animation
+                    for i in 1..=5 {
                         print!("\x1b[2J"); // Clear
-                        print!("\x1b[1;1HPopWin Simulation (macOS TUI Mode)");
+                        print!("\x1b[1;1HPopWin Simulation (TUI Mode)");
                         
-                        // Re-draw UI (simplified)
+                        let frame_color = if i < 3 { 90 } else { 37 }; // Dark gray to White
+                        
+                        // Top border
                         print!("\x1b[{};{}H\x1b[{}m+-------------------------+", y, x, frame_color);
+                        // Title
                         print!("\x1b[{};{}H|  \x1b[1mPopWin Toolbar\x1b[0m\x1b[{}m         |", y+1, x, frame_color);
+                        // Separator
                         print!("\x1b[{};{}H|-------------------------|", y+2, x);
-                        print!("\x1b[{};{}H|  [C] Copy   [V] Paste    |", y+3, x);
-                        print!("\x1b[{};{}H|  [S] Search [E] EN       |", y+4, x);
-                        print!("\x1b[{};{}H|                         |", y+5, x);
-                        print!("\x1b[{};{}H|  Selected: \x1b[36m{}\x1b[0m\x1b[{}m|", y+6, x, text_display, frame_color); 
-                        // Translation result
-                        print!("\x1b[{};{}H|-------------------------|", y+7, x);
-                        let safe_translation: String = translation.chars().take(20).collect();
-                        print!("\x1b[{};{}H|  \x1b[33m{: <23}\x1b[0m\x1b[{}m", y+8, x, safe_translation, frame_color); // Simplified padding
-                        print!("\x1b[{};{}H|", y+8, x+26); 
-                        
+                        // Buttons
+                        for (row, line) in button_rows.iter().enumerate() {
+                            print!("\x1b[{};{}H|  {: <23}|", y+3+row, x, line);
+                        }
+                        print!("\x1b[{};{}H|                         |", y+3+rows, x);
+                        // Selected Text
+                        print!("\x1b[{};{}H|  Selected: \x1b[36m{}\x1b[0m\x1b[{}m|", y+4+rows, x, text_display, frame_color); 
                         // Bottom border
-                        print!("\x1b[{};{}H+-------------------------+\x1b[0m", y+9, x);
+                        print!("\x1b[{};{}H+-------------------------+\x1b[0m", y+5+rows, x);
+                        
+                        print!("\x1b[{};{}H(Animation Frame: {}/5)", y+11+rows, x, i);
+                        stdout().flush().unwrap();
+                        sleep(Duration::from_millis(150));
+                    }
 
-                        print!("\x1b[{};{}H\x1b[32m> Translation Received: {}\x1b[0m", y+12, x, translation);
+                    // Interaction simulation: Click EN
+                    sleep(Duration::from_secs(1));
+                    if let Some(action) = registry.get("translate") {
+                        print!("\x1b[{};{}H\x1b[32m> User clicked [{}] (Requesting Translation...)\x1b[0m", y+8+rows, x, action.label());
                         stdout().flush().unwrap();
-                        
-                        sleep(Duration::from_secs(3));
-                        break; // End simulation loop
+
+                        let ctx = actions::ActionContext { selection: &last_selection, sender: &tx, cancel: &cancel, request_id: actions::RequestId::next(), option: None, force_refresh: false };
+                        action.run(&ctx);
                     }
-                    AppEvent::SelectionCleared => {}
                 }
+                AppEvent::TranslationReceived { translation: result, .. } => {
+                     let translation = &result.text;
+                     let (x, y) = last_pos;
+                     let last_text = &last_selection.text;
+                     let button_rows = tui_button_rows(&registry, &last_selection);
+                     let rows = button_rows.len();
+                     let text_display = if last_text.chars().count() > 10 {
+                        format!("{}...", last_text.chars().take(10).collect::<String>())
+                    } else {
+                        format!("{: <13}", last_text)
+                    };
+                    let frame_color = 37;
+
+                    // Redraw window with translation result
+                    print!("\x1b[2J"); // Clear
+                    print!("\x1b[1;1HPopWin Simulation (TUI Mode)");
+                    
+                    // Re-draw UI (simplified)
+                    print!("\x1b[{};{}H\x1b[{}m+-------------------------+", y, x, frame_color);
+                    print!("\x1b[{};{}H|  \x1b[1mPopWin Toolbar\x1b[0m\x1b[{}m         |", y+1, x, frame_color);
+                    print!("\x1b[{};{}H|-------------------------|", y+2, x);
+                    for (row, line) in button_rows.iter().enumerate() {
+                        print!("\x1b[{};{}H|  {: <23}|", y+3+row, x, line);
+                    }
+                    print!("\x1b[{};{}H|                         |", y+3+rows, x);
+                    print!("\x1b[{};{}H|  Selected: \x1b[36m{}\x1b[0m\x1b[{}m|", y+4+rows, x, text_display, frame_color); 
+                    // Translation result
+                    print!("\x1b[{};{}H|-------------------------|", y+5+rows, x);
+                    let safe_translation: String = translation.chars().take(20).collect();
+                    print!("\x1b[{};{}H|  \x1b[33m{: <23}\x1b[0m\x1b[{}m", y+6+rows, x, safe_translation, frame_color); // Simplified padding
+                    print!("\x1b[{};{}H|", y+6+rows, x+26); 
+                    
+                    // Bottom border
+                    print!("\x1b[{};{}H+-------------------------+\x1b[0m", y+7+rows, x);
+
+                    print!("\x1b[{};{}H\x1b[32m> Translation Received: {}\x1b[0m", y+10+rows, x, translation);
+                    for (i, detail) in tui_translation_details(&result).iter().enumerate() {
+                        print!("\x1b[{};{}H\x1b[90m  {}\x1b[0m", y+11+rows+i, x, detail);
+                    }
+                    stdout().flush().unwrap();
+                    
+                    sleep(Duration::from_secs(3));
+                    break; // End simulation loop
+                }
+                AppEvent::TranslationFailed { error, .. } => {
+                    println!("\n\x1b[31m> {} ({})\x1b[0m", error.summary(), error);
+                    sleep(Duration::from_secs(3));
+                    break; // End simulation loop
+                }
+                AppEvent::TranslationProgress { done, total, .. } => {
+                    println!("\n\x1b[90m> 翻訳中... {}/{}\x1b[0m", done, total);
+                }
+                AppEvent::RoundTripReceived { round_trip, .. } => {
+                    println!("\n\x1b[32m> {}\x1b[0m", round_trip.translation.text);
+                    println!("\x1b[90m> back: {} ({:.0}%)\x1b[0m", round_trip.back.text, round_trip.similarity * 100.0);
+                }
+                AppEvent::ComparisonStarted { providers, .. } => {
+                    println!("\n\x1b[90m> 比較中: {}\x1b[0m", providers.join(", "));
+                }
+                AppEvent::ComparisonResult { provider, result, .. } => match result {
+                    Ok(translation) => println!("\n\x1b[32m> {}: {}\x1b[0m", provider, translation.text),
+                    Err(error) => println!("\n\x1b[31m> {}: {} ({})\x1b[0m", provider, error.summary(), error),
+                },
+                AppEvent::StreamChunk { text, .. } => {
+                    print!("{}", text);
+                    stdout().flush().unwrap();
+                }
+                AppEvent::StreamFinished { result, .. } => {
+                    if let Err(e) = result {
+                        println!("\n\x1b[31m> stream failed: {}\x1b[0m", e);
+                    }
+                }
+                AppEvent::ActionOutput { action_id, result, .. } => {
+                    match result {
+                        Ok(output) => println!("\n\x1b[32m> {}: {}\x1b[0m", action_id, output),
+                        Err(e) => println!("\n\x1b[31m> {} failed: {}\x1b[0m", action_id, e),
+                    }
+                }
+                AppEvent::SelectionCleared => {}
             }
         }
-        
-        // Reset cursor and clear
-        print!("\x1b[?25h\nDone.\n");
-        Ok(())
     }
+    
+    // Reset cursor and clear
+    print!("\x1b[?25h\nDone.\n");
+    Ok(())
+}
+
+// The egui toolbar needs an X server on Linux (XWayland will do); elsewhere it's the TUI
+#[cfg(not(target_os = "windows"))]
+fn toolbar_supported() -> bool {
+    cfg!(all(unix, not(target_os = "macos"))) && std::env::var_os("DISPLAY").is_some()
+}
+
+// Lay out the registry's buttons as "[Label]" cells wrapped to the TUI box width
+#[cfg(not(target_os = "windows"))]
+fn tui_button_rows(registry: &actions::ActionRegistry, selection: &actions::Selection) -> Vec<String> {
+    const WIDTH: usize = 23;
+    let mut rows = Vec::new();
+    let mut line = String::new();
+    for action in registry.toolbar(selection) {
+        let cell = format!("[{}]", action.label());
+        if !line.is_empty() && line.chars().count() + 1 + cell.chars().count() > WIDTH {
+            rows.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&cell);
+    }
+    if !line.is_empty() {
+        rows.push(line);
+    }
+    rows
+}
+
+#[cfg(not(target_os = "windows"))]
+fn tui_translation_details(translation: &translation::Translation) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(lang) = &translation.detected_source {
+        lines.push(format!("detected: {}", lang));
+    }
+    if let Some(romanization) = translation.romanization.as_ref().or(translation.source_romanization.as_ref()) {
+        lines.push(romanization.clone());
+    }
+    for entry in &translation.dictionary {
+        lines.push(format!("{}: {}", entry.part_of_speech, entry.terms.join(", ")));
+    }
+    if !translation.alternatives.is_empty() {
+        lines.push(format!("alt: {}", translation.alternatives.join(" / ")));
+    }
+    if !translation.glossary_terms.is_empty() {
+        lines.push(format!("glossary: {}", translation.glossary_terms.join(", ")));
+    }
+    lines
 }
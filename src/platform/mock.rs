@@ -0,0 +1,210 @@
+// In-memory desktop for running PopWin without one: the simulation mode, and anything that
+// wants to drive the pipeline with a script and look at what came out.
+//
+// A `MockDesktop` holds the focused app's selection and a fake clipboard. Its injector acts
+// like an editor (Ctrl+C copies the selection, Ctrl+V replaces it), and a
+// `ScriptedSelectionSource` plays mouse presses and releases against it.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+
+use super::{ClipboardBackend, ClipboardSnapshot, InputInjector, SelectionSource, Shortcut};
+use crate::hooks::gesture::{GestureRecognizer, InputEvent};
+use crate::AppEvent;
+
+// Clones share the contents, so one clone can play the app that answers Ctrl+C
+#[derive(Clone, Default)]
+pub struct FakeClipboard {
+    state: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    formats: Vec<(u32, Vec<u8>)>,
+    sequence: u64,
+}
+
+impl FakeClipboard {
+    pub const TEXT_FORMAT: u32 = 13;
+
+    // What an app does on Ctrl+C: replace everything with its own content
+    pub fn put_text(&self, text: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.formats = vec![(Self::TEXT_FORMAT, text.as_bytes().to_vec())];
+        state.sequence += 1;
+    }
+
+    pub fn contents(&self) -> ClipboardSnapshot {
+        ClipboardSnapshot { formats: self.state.lock().unwrap().formats.clone() }
+    }
+}
+
+impl ClipboardBackend for FakeClipboard {
+    fn sequence(&self) -> Option<u64> {
+        Some(self.state.lock().unwrap().sequence)
+    }
+
+    fn snapshot(&mut self) -> Result<ClipboardSnapshot, String> {
+        Ok(self.contents())
+    }
+
+    fn restore(&mut self, snapshot: &ClipboardSnapshot) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        state.formats = snapshot.formats.clone();
+        state.sequence += 1;
+        Ok(())
+    }
+
+    fn text(&mut self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let (_, data) = state.formats.iter().find(|(f, _)| *f == Self::TEXT_FORMAT)?;
+        String::from_utf8(data.clone()).ok()
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        self.put_text(text);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct DesktopState {
+    selection: String,
+    sent: Vec<Shortcut>,
+}
+
+#[derive(Clone, Default)]
+pub struct MockDesktop {
+    clipboard: FakeClipboard,
+    state: Arc<Mutex<DesktopState>>,
+}
+
+impl MockDesktop {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The user selects `text` in the focused app
+    pub fn select(&self, text: &str) {
+        self.state.lock().unwrap().selection = text.to_string();
+    }
+
+    // What the focused app has selected now (after a paste, the pasted text)
+    pub fn selection(&self) -> String {
+        self.state.lock().unwrap().selection.clone()
+    }
+
+    // Shortcuts received so far, oldest first
+    pub fn sent(&self) -> Vec<Shortcut> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    pub fn clipboard(&self) -> FakeClipboard {
+        self.clipboard.clone()
+    }
+
+    pub fn injector(&self) -> MockInjector {
+        MockInjector { desktop: self.clone() }
+    }
+}
+
+pub struct MockInjector {
+    desktop: MockDesktop,
+}
+
+impl InputInjector for MockInjector {
+    fn send(&self, shortcut: Shortcut) {
+        let clipboard = &self.desktop.clipboard;
+        let mut state = self.desktop.state.lock().unwrap();
+        state.sent.push(shortcut);
+        match shortcut {
+            // Like most editors, copying nothing leaves the clipboard alone
+            Shortcut::Copy | Shortcut::Cut if !state.selection.is_empty() => {
+                clipboard.put_text(&state.selection);
+                if shortcut == Shortcut::Cut {
+                    state.selection.clear();
+                }
+            }
+            Shortcut::Copy | Shortcut::Cut => {}
+            Shortcut::Paste => {
+                let pasted = clipboard.clone().text().unwrap_or_default();
+                log::info!("Mock desktop: pasted {:?} over {:?}", pasted, state.selection);
+                state.selection = pasted;
+            }
+        }
+    }
+}
+
+// Only reports shortcuts; for desktops we can't send keystrokes to
+pub struct LogInjector;
+
+impl InputInjector for LogInjector {
+    fn send(&self, shortcut: Shortcut) {
+        println!("Action: {:?} (Simulated)", shortcut);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ScriptStep {
+    Wait(Duration),
+    // Select text in the focused app (no events; the app just has a selection now)
+    Select(String),
+    // Mouse or keyboard input, recognized and acted on the way the real hook does
+    Input(InputEvent),
+}
+
+pub struct ScriptedSelectionSource {
+    desktop: MockDesktop,
+    steps: Vec<ScriptStep>,
+}
+
+impl ScriptedSelectionSource {
+    pub fn new(desktop: MockDesktop, steps: Vec<ScriptStep>) -> Self {
+        Self { desktop, steps }
+    }
+
+    // One drag-selection after 3 seconds, for the simulation mode
+    pub fn demo(desktop: MockDesktop) -> Self {
+        Self::new(
+            desktop,
+            vec![
+                ScriptStep::Wait(Duration::from_secs(3)),
+                ScriptStep::Input(InputEvent::Move { x: 140, y: 200 }),
+                ScriptStep::Input(InputEvent::ButtonDown { x: 140, y: 200 }),
+                ScriptStep::Select("Simulation Text".to_string()),
+                ScriptStep::Wait(Duration::from_millis(150)),
+                ScriptStep::Input(InputEvent::Move { x: 200, y: 200 }),
+                ScriptStep::Input(InputEvent::ButtonUp { x: 200, y: 200 }),
+            ],
+        )
+    }
+}
+
+impl SelectionSource for ScriptedSelectionSource {
+    fn start(&mut self, sender: Sender<AppEvent>) -> Result<(), String> {
+        let desktop = self.desktop.clone();
+        let steps = self.steps.clone();
+        thread::spawn(move || {
+            let started = Instant::now();
+            let mut recognizer = GestureRecognizer::new();
+            for step in steps {
+                match step {
+                    ScriptStep::Wait(duration) => thread::sleep(duration),
+                    ScriptStep::Select(text) => desktop.select(&text),
+                    ScriptStep::Input(event) => {
+                        let at_ms = started.elapsed().as_millis() as u64;
+                        // Let the fetch finish so later steps see its effect on the desktop
+                        if let Some(fetch) = crate::hooks::feed(&mut recognizer, event, at_ms, &sender) {
+                            let _ = fetch.join();
+                        }
+                    }
+                }
+            }
+            log::info!("Scripted selection finished; the mock desktop received {:?}", desktop.sent());
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+// Seams between PopWin and the desktop: where selections come from, how keystrokes are sent,
+// and the clipboard. The real implementations live next to the code that uses them
+// (hooks, actions, automation); `mock` has scriptable in-memory ones that drive the whole
+// pipeline (mouse-up → text fetch → toolbar → action) without a desktop.
+
+use std::sync::{Arc, Mutex};
+
+use crossbeam_channel::Sender;
+use once_cell::sync::Lazy;
+
+use crate::AppEvent;
+
+pub mod mock;
+
+// Reports selections as `SelectionDetected` / `SelectionCleared` events
+pub trait SelectionSource: Send {
+    // Fails when the platform facility isn't there (no X server, ...)
+    fn start(&mut self, sender: Sender<AppEvent>) -> Result<(), String>;
+    fn stop(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+    Copy,
+    Cut,
+    Paste,
+}
+
+// Sends keystrokes to the focused application
+pub trait InputInjector: Send + Sync {
+    fn send(&self, shortcut: Shortcut);
+}
+
+// Every format on the clipboard as raw bytes, keyed by the platform's format id
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClipboardSnapshot {
+    pub formats: Vec<(u32, Vec<u8>)>,
+}
+
+pub trait ClipboardBackend: Send {
+    // Counter bumped on every clipboard change, if the platform keeps one
+    fn sequence(&self) -> Option<u64>;
+    fn snapshot(&mut self) -> Result<ClipboardSnapshot, String>;
+    fn restore(&mut self, snapshot: &ClipboardSnapshot) -> Result<(), String>;
+    fn text(&mut self) -> Option<String>;
+    fn set_text(&mut self, text: &str) -> Result<(), String>;
+}
+
+type SharedClipboard = Arc<Mutex<Box<dyn ClipboardBackend>>>;
+
+struct Platform {
+    injector: Arc<dyn InputInjector>,
+    clipboard: SharedClipboard,
+}
+
+static PLATFORM: Lazy<Mutex<Option<Platform>>> = Lazy::new(|| Mutex::new(None));
+
+// Replace the injector and clipboard used from now on (the native ones until this is called)
+pub fn install(injector: Arc<dyn InputInjector>, clipboard: Box<dyn ClipboardBackend>) {
+    *PLATFORM.lock().unwrap() = Some(Platform { injector, clipboard: Arc::new(Mutex::new(clipboard)) });
+}
+
+fn current() -> (Arc<dyn InputInjector>, SharedClipboard) {
+    let mut platform = PLATFORM.lock().unwrap();
+    let platform = platform.get_or_insert_with(|| Platform {
+        injector: native_injector(),
+        clipboard: Arc::new(Mutex::new(native_clipboard())),
+    });
+    (platform.injector.clone(), platform.clipboard.clone())
+}
+
+pub fn injector() -> Arc<dyn InputInjector> {
+    current().0
+}
+
+pub fn with_clipboard<R>(f: impl FnOnce(&mut dyn ClipboardBackend) -> R) -> R {
+    let clipboard = current().1;
+    let mut clipboard = clipboard.lock().unwrap();
+    f(clipboard.as_mut())
+}
+
+fn native_injector() -> Arc<dyn InputInjector> {
+    #[cfg(target_os = "windows")]
+    return Arc::new(crate::actions::SendInputInjector);
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    match crate::actions::XTestInjector::connect() {
+        Ok(injector) => return Arc::new(injector),
+        Err(e) => log::info!("No X11 input injection ({}); shortcuts are only logged", e),
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    Arc::new(mock::LogInjector)
+}
+
+fn native_clipboard() -> Box<dyn ClipboardBackend> {
+    #[cfg(target_os = "windows")]
+    return Box::new(crate::automation::WindowsClipboard);
+
+    #[cfg(not(target_os = "windows"))]
+    Box::new(crate::automation::SystemClipboard)
+}
+
+fn native_selection_source() -> Option<Box<dyn SelectionSource>> {
+    #[cfg(target_os = "windows")]
+    return Some(Box::new(crate::hooks::MouseHookSource));
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    return Some(Box::new(crate::hooks::X11SelectionSource));
+
+    #[allow(unreachable_code)]
+    None
+}
+
+// The desktop's own selection source, or the scripted demo on mocks when there is none
+pub fn start_selection_source(sender: &Sender<AppEvent>) -> Box<dyn SelectionSource> {
+    if let Some(mut source) = native_selection_source() {
+        match source.start(sender.clone()) {
+            Ok(()) => return source,
+            Err(e) => log::info!("No selection source on this desktop ({}); simulating a selection", e),
+        }
+    }
+    let desktop = mock::MockDesktop::new();
+    install(Arc::new(desktop.injector()), Box::new(desktop.clipboard()));
+    let mut demo = mock::ScriptedSelectionSource::demo(desktop);
+    // Replaying a script can't fail
+    let _ = demo.start(sender.clone());
+    Box::new(demo)
+}
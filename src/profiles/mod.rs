@@ -0,0 +1,100 @@
+// Per-application rules: where PopWin stays out of the way, and which buttons it shows.
+// Rules are tried in order and the first match wins; a rule without `app` and `class`
+// matches every application, so it can close the list as the default.
+//
+//   [[apps]]
+//   app = "KeePassXC"                # process name, any case, ".exe" optional
+//   enabled = false                  # no toolbar, no Ctrl+C probe, and no clipboard history
+//
+//   [[apps]]
+//   app = "OUTLOOK.EXE"
+//   actions = ["copy", "translate"]  # only these buttons
+//
+//   [[apps]]
+//   class = "CASCADIA_HOSTING_WINDOW_CLASS"   # window class (Windows Terminal)
+//   actions = ["copy", "paste", "shell-tools.*"]
+//
+//   [[apps]]                         # everywhere else
+//   hide = ["shell-tools.*"]
+//
+// Action ids ending in `*` match by prefix, e.g. "shell-tools.*" for a whole pack.
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::automation::AppInfo;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppRule {
+    // Process name (e.g. "OUTLOOK.EXE", ".exe" optional); on X11 the name in /proc (e.g. "gnome-terminal-")
+    pub app: Option<String>,
+    // Top-level window class (e.g. "ConsoleWindowClass"; WM_CLASS class on X11)
+    pub class: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    // Only these action ids get buttons; unset means all
+    pub actions: Option<Vec<String>>,
+    #[serde(default)]
+    pub hide: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl AppRule {
+    fn matches(&self, app: &AppInfo) -> bool {
+        let field_matches = |pattern: &Option<String>, value: &Option<String>, same: fn(&str, &str) -> bool| {
+            match (pattern, value) {
+                (None, _) => true,
+                (Some(pattern), Some(value)) => same(pattern, value),
+                (Some(_), None) => false,
+            }
+        };
+        field_matches(&self.app, &app.process, crate::automation::same_app)
+            && field_matches(&self.class, &app.class, |a, b| a.eq_ignore_ascii_case(b))
+    }
+
+    // Whether the toolbar shows the action with `id` under this rule
+    pub fn allows(&self, id: &str) -> bool {
+        let listed = |patterns: &[String]| patterns.iter().any(|pattern| id_matches(pattern, id));
+        self.actions.as_deref().is_none_or(listed) && !listed(&self.hide)
+    }
+}
+
+fn id_matches(pattern: &str, id: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => id.starts_with(prefix),
+        None => pattern == id,
+    }
+}
+
+static RULES: Lazy<Mutex<Vec<AppRule>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// The [[apps]] rules from config; no rules until this is called
+pub fn configure(rules: &[AppRule]) {
+    *RULES.lock().unwrap() = rules.to_vec();
+}
+
+// The rule that applies to `app`; an app no rule matches gets everything
+pub fn profile_for(app: &AppInfo) -> AppRule {
+    RULES.lock().unwrap().iter().find(|rule| rule.matches(app)).cloned().unwrap_or(AppRule {
+        app: None,
+        class: None,
+        enabled: true,
+        actions: None,
+        hide: Vec::new(),
+    })
+}
+
+// Whether PopWin should react to selections in `app` at all
+pub fn is_enabled(app: &AppInfo) -> bool {
+    let enabled = profile_for(app).enabled;
+    if !enabled {
+        log::debug!("PopWin is disabled for {:?}", app);
+    }
+    enabled
+}
@@ -0,0 +1,342 @@
+// Translation cache: an in-memory LRU in front of a small SQLite file, shared by all providers.
+// Keys are provider/source/target plus whitespace-normalized text, so re-selecting the same
+// error message with a different trailing newline still hits.
+
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lru::LruCache;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+
+use super::glossary::fingerprint;
+use super::{Translation, TranslationRequest, TranslationResult, Translator};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub memory_entries: usize,
+    // 0 keeps the cache in memory only
+    pub disk_entries: usize,
+    pub ttl_days: u64,
+    // Defaults to translations.sqlite3 in the user cache directory
+    pub path: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            memory_entries: 500,
+            disk_entries: 20_000,
+            ttl_days: 30,
+            path: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    text: String,
+    created_at: u64,
+}
+
+pub struct TranslationCache {
+    memory: Mutex<LruCache<String, Entry>>,
+    store: Option<Mutex<Connection>>,
+    ttl: Duration,
+    disk_entries: usize,
+}
+
+impl TranslationCache {
+    pub fn memory_only(config: &CacheConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.memory_entries.max(1)).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            memory: Mutex::new(LruCache::new(capacity)),
+            store: None,
+            ttl: Duration::from_secs(config.ttl_days.saturating_mul(24 * 60 * 60)),
+            disk_entries: 0,
+        }
+    }
+
+    pub fn open(config: &CacheConfig, path: &Path) -> Result<Self, String> {
+        let mut cache = Self::memory_only(config);
+        if config.disk_entries == 0 {
+            return Ok(cache);
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS translations (
+                 key         TEXT PRIMARY KEY,
+                 translation TEXT NOT NULL,
+                 created_at  INTEGER NOT NULL,
+                 last_used   INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS translations_last_used ON translations (last_used);",
+        )
+        .map_err(|e| e.to_string())?;
+        let expired_before = now().saturating_sub(cache.ttl.as_secs());
+        conn.execute("DELETE FROM translations WHERE created_at < ?1", params![expired_before as i64])
+            .map_err(|e| e.to_string())?;
+
+        cache.store = Some(Mutex::new(conn));
+        cache.disk_entries = config.disk_entries;
+        Ok(cache)
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        if let Some(entry) = self.memory.lock().unwrap().get(key) {
+            if self.is_fresh(entry) {
+                return Some(entry.text.clone());
+            }
+        }
+
+        let store = self.store.as_ref()?.lock().unwrap();
+        let row: Option<(String, i64)> = store
+            .query_row(
+                "SELECT translation, created_at FROM translations WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                log::warn!("Translation cache read failed: {}", e);
+                None
+            });
+        let entry = row.map(|(text, created_at)| Entry { text, created_at: created_at as u64 })?;
+        if !self.is_fresh(&entry) {
+            return None;
+        }
+        let _ = store.execute("UPDATE translations SET last_used = ?1 WHERE key = ?2", params![now() as i64, key]);
+        drop(store);
+
+        self.memory.lock().unwrap().put(key.to_string(), entry.clone());
+        Some(entry.text)
+    }
+
+    pub fn put(&self, key: &str, text: &str) {
+        let entry = Entry { text: text.to_string(), created_at: now() };
+        self.memory.lock().unwrap().put(key.to_string(), entry.clone());
+
+        let Some(store) = &self.store else { return };
+        let store = store.lock().unwrap();
+        let result = store
+            .execute(
+                "INSERT OR REPLACE INTO translations (key, translation, created_at, last_used) VALUES (?1, ?2, ?3, ?3)",
+                params![key, entry.text, entry.created_at as i64],
+            )
+            .and_then(|_| {
+                // Keep the file bounded: drop the least recently used rows beyond the limit
+                store.execute(
+                    "DELETE FROM translations WHERE key IN (
+                         SELECT key FROM translations ORDER BY last_used DESC LIMIT -1 OFFSET ?1
+                     )",
+                    params![self.disk_entries as i64],
+                )
+            });
+        if let Err(e) = result {
+            log::warn!("Translation cache write failed: {}", e);
+        }
+    }
+
+    fn is_fresh(&self, entry: &Entry) -> bool {
+        now().saturating_sub(entry.created_at) < self.ttl.as_secs()
+    }
+}
+
+// Wraps any provider; `request.bypass_cache` skips the lookup but still refreshes the entry
+pub struct CachedTranslator {
+    inner: Arc<dyn Translator>,
+    cache: Arc<TranslationCache>,
+}
+
+impl CachedTranslator {
+    pub fn new(inner: Arc<dyn Translator>, cache: Arc<TranslationCache>) -> Self {
+        Self { inner, cache }
+    }
+}
+
+impl Translator for CachedTranslator {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+        let key = cache_key(&format!("{}@{}", self.inner.id(), self.inner.fingerprint()), request);
+        if !request.bypass_cache {
+            if let Some(stored) = self.cache.get(&key) {
+                log::debug!("Translation cache hit ({})", self.inner.id());
+                // Entries written before translations carried details are plain text
+                return Ok(serde_json::from_str(&stored).unwrap_or_else(|_| Translation::plain(stored)));
+            }
+        }
+        let translation = self.inner.translate(request)?;
+        match serde_json::to_string(&translation) {
+            Ok(stored) => self.cache.put(&key, &stored),
+            Err(e) => log::warn!("Translation cache write failed: {}", e),
+        }
+        Ok(translation)
+    }
+
+    // Chunking happens above the cache, so each chunk gets its own entry
+    fn max_chunk_chars(&self) -> usize {
+        self.inner.max_chunk_chars()
+    }
+
+    fn native_glossary(&self) -> bool {
+        self.inner.native_glossary()
+    }
+
+    fn fingerprint(&self) -> String {
+        self.inner.fingerprint()
+    }
+}
+
+pub fn cache_key(provider: &str, request: &TranslationRequest) -> String {
+    let mut key = format!(
+        "{}\u{1f}{}\u{1f}{}\u{1f}{}",
+        provider,
+        request.source.as_deref().unwrap_or("auto").to_ascii_lowercase(),
+        request.target.to_ascii_lowercase(),
+        normalize(&request.text)
+    );
+    // A native glossary changes the result, so editing the glossary must miss
+    if !request.glossary.is_empty() {
+        key.push('\u{1f}');
+        key.push_str(&fingerprint(&request.glossary));
+    }
+    key
+}
+
+// Trim, unify line endings and collapse runs of spaces/tabs; line breaks are kept
+// because they change what the provider returns
+fn normalize(text: &str) -> String {
+    text.trim()
+        .replace("\r\n", "\n")
+        .lines()
+        .map(|line| line.split([' ', '\t']).filter(|w| !w.is_empty()).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::actions::CancelToken;
+    use crate::translation::GlossaryEntry;
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest {
+            text: text.to_string(),
+            source: None,
+            target: "ja".to_string(),
+            bypass_cache: false,
+            glossary: Vec::new(),
+            cancel: CancelToken::new(),
+        }
+    }
+
+    // Answers with how many times it has been asked
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    impl Translator for Counter {
+        fn id(&self) -> &str {
+            "counter"
+        }
+
+        fn translate(&self, _request: &TranslationRequest) -> TranslationResult {
+            Ok(Translation::plain((self.0.fetch_add(1, Ordering::SeqCst) + 1).to_string()))
+        }
+    }
+
+    fn config(memory_entries: usize, disk_entries: usize) -> CacheConfig {
+        CacheConfig { memory_entries, disk_entries, ..CacheConfig::default() }
+    }
+
+    #[test]
+    fn keys_ignore_whitespace_noise_and_language_case() {
+        let key = |request: &TranslationRequest| cache_key("google@", request);
+        let base = key(&request("Hello  world\n"));
+        assert_eq!(key(&request("  Hello\tworld\r\n")), base);
+        assert_eq!(key(&TranslationRequest { source: Some("AUTO".to_string()), ..request("Hello world") }), base);
+        assert_eq!(key(&TranslationRequest { target: "JA".to_string(), ..request("Hello world") }), base);
+
+        assert_ne!(key(&request("Hello\nworld")), base);
+        assert_ne!(key(&request("hello world")), base);
+        assert_ne!(key(&TranslationRequest { source: Some("en".to_string()), ..request("Hello world") }), base);
+        assert_ne!(key(&TranslationRequest { target: "en".to_string(), ..request("Hello world") }), base);
+        assert_ne!(cache_key("deepl@", &request("Hello world")), base);
+        let glossary = vec![GlossaryEntry { source: "world".to_string(), target: "世界".to_string() }];
+        assert_ne!(key(&TranslationRequest { glossary, ..request("Hello world") }), base);
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let mut cache = TranslationCache::memory_only(&config(10, 0));
+        cache.put("key", "value");
+        assert_eq!(cache.get("key").as_deref(), Some("value"));
+        cache.ttl = Duration::ZERO;
+        assert_eq!(cache.get("key"), None);
+    }
+
+    #[test]
+    fn expired_rows_are_dropped_when_the_file_is_opened() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("translations.sqlite3");
+        let cache = TranslationCache::open(&config(10, 10), &path).unwrap();
+        cache.put("old", "value");
+        cache.put("new", "value");
+        let store = cache.store.as_ref().unwrap().lock().unwrap();
+        store.execute("UPDATE translations SET created_at = 0 WHERE key = 'old'", []).unwrap();
+        drop(store);
+        drop(cache);
+
+        let cache = TranslationCache::open(&config(10, 10), &path).unwrap();
+        assert_eq!(cache.get("old"), None);
+        assert_eq!(cache.get("new").as_deref(), Some("value"));
+    }
+
+    #[test]
+    fn the_file_keeps_the_most_recently_used_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = TranslationCache::open(&config(1, 2), &dir.path().join("translations.sqlite3")).unwrap();
+        cache.put("a", "1");
+        cache.put("b", "2");
+        // Timestamps are in seconds, so order the rows by hand
+        let store = cache.store.as_ref().unwrap();
+        store.lock().unwrap().execute("UPDATE translations SET last_used = 1 WHERE key = 'a'", []).unwrap();
+        store.lock().unwrap().execute("UPDATE translations SET last_used = 2 WHERE key = 'b'", []).unwrap();
+        cache.put("c", "3");
+
+        let count: i64 =
+            store.lock().unwrap().query_row("SELECT COUNT(*) FROM translations", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b").as_deref(), Some("2"));
+        assert_eq!(cache.get("c").as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn bypass_skips_the_lookup_but_refreshes_the_entry() {
+        let cache = Arc::new(TranslationCache::memory_only(&config(10, 0)));
+        let cached = CachedTranslator::new(Arc::new(Counter::default()), cache);
+        assert_eq!(cached.translate(&request("hello")).unwrap().text, "1");
+        assert_eq!(cached.translate(&request("hello")).unwrap().text, "1");
+        let bypass = TranslationRequest { bypass_cache: true, ..request("hello") };
+        assert_eq!(cached.translate(&bypass).unwrap().text, "2");
+        assert_eq!(cached.translate(&request("hello")).unwrap().text, "2");
+    }
+}
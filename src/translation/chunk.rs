@@ -0,0 +1,313 @@
+// Splitting long selections into provider-sized chunks and translating them in parallel.
+//
+// Chunks are contiguous slices of the original text. Whitespace at their edges (line breaks,
+// indentation) is kept aside and glued back around the translation, so paragraphs survive
+// providers that trim their input.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use super::{Translation, TranslationError, TranslationRequest, TranslationResult, Translator};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub lead: String,
+    pub body: String,
+    pub trail: String,
+}
+
+// Sentence enders. CJK ones end a sentence on their own; ASCII ones need whitespace after them.
+fn is_cjk_terminal(c: char) -> bool {
+    matches!(c, '。' | '！' | '？' | '．' | '｡')
+}
+
+fn is_ascii_terminal(c: char) -> bool {
+    matches!(c, '.' | '!' | '?')
+}
+
+// Closing quotes/brackets that belong to the sentence they follow
+fn is_closer(c: char) -> bool {
+    matches!(c, '」' | '』' | '）' | ')' | '"' | '\'' | '”' | '’' | ']' | '】')
+}
+
+// Break text into sentence-sized units; concatenating them gives back the input
+pub(super) fn sentence_units(text: &str) -> Vec<&str> {
+    let mut units = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let mut end = None;
+        if c == '\n' {
+            end = Some(i + c.len_utf8());
+        } else if is_cjk_terminal(c) || is_ascii_terminal(c) {
+            let mut j = i + c.len_utf8();
+            while let Some(&(k, next)) = chars.peek() {
+                if is_closer(next) || (is_ascii_terminal(c) && is_ascii_terminal(next)) {
+                    j = k + next.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let followed_by_space = chars.peek().is_none_or(|&(_, next)| next.is_whitespace());
+            if is_cjk_terminal(c) || followed_by_space {
+                end = Some(j);
+            }
+        }
+        if let Some(end) = end {
+            units.push(&text[start..end]);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        units.push(&text[start..]);
+    }
+    units
+}
+
+// Split an over-long unit at whitespace where possible, otherwise at `max` chars
+fn hard_split(unit: &str, max: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = unit;
+    while rest.chars().count() > max {
+        let limit = rest.char_indices().nth(max).map(|(i, _)| i).unwrap_or(rest.len());
+        let cut = rest[..limit]
+            .rfind(char::is_whitespace)
+            .filter(|&i| i > 0)
+            .map(|i| i + rest[i..].chars().next().map_or(1, |c| c.len_utf8()))
+            .unwrap_or(limit);
+        parts.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    if !rest.is_empty() {
+        parts.push(rest);
+    }
+    parts
+}
+
+pub fn split_chunks(text: &str, max_chars: usize) -> Vec<Chunk> {
+    let max_chars = max_chars.max(1);
+    let mut pieces: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for unit in sentence_units(text) {
+        for part in hard_split(unit, max_chars) {
+            let len = part.chars().count();
+            // Prefer to close a chunk at a paragraph break once it is reasonably full
+            let paragraph_break = current.ends_with("\n\n") && current_len >= max_chars / 2;
+            if current_len > 0 && (current_len + len > max_chars || paragraph_break) {
+                pieces.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            current.push_str(part);
+            current_len += len;
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+        .into_iter()
+        .map(|piece| {
+            let body = piece.trim();
+            let lead_len = piece.len() - piece.trim_start().len();
+            Chunk {
+                lead: piece[..lead_len].to_string(),
+                body: body.to_string(),
+                trail: piece[lead_len + body.len()..].to_string(),
+            }
+        })
+        .collect()
+}
+
+// Translate `request.text` chunk by chunk with at most `max_parallel` requests in flight.
+// `on_progress(done, total)` is called after each chunk; the first failing chunk fails the whole.
+pub fn translate_chunked(
+    translator: &dyn Translator,
+    request: &TranslationRequest,
+    max_parallel: usize,
+    on_progress: &(dyn Fn(usize, usize) + Sync),
+) -> TranslationResult {
+    let chunks = split_chunks(&request.text, translator.max_chunk_chars());
+    if chunks.len() <= 1 {
+        return translator.translate(request);
+    }
+
+    let total = chunks.len();
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<Option<TranslationResult>>> = Mutex::new(vec![None; total]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..max_parallel.clamp(1, total) {
+            scope.spawn(|| loop {
+                if failed.load(Ordering::SeqCst) || request.cancel.is_cancelled() {
+                    return;
+                }
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(chunk) = chunks.get(index) else { return };
+
+                let result = if chunk.body.is_empty() {
+                    Ok(Translation::default())
+                } else {
+                    translator.translate(&TranslationRequest { text: chunk.body.clone(), ..request.clone() })
+                };
+                if result.is_err() {
+                    failed.store(true, Ordering::SeqCst);
+                }
+                results.lock().unwrap()[index] = Some(result);
+                on_progress(done.fetch_add(1, Ordering::SeqCst) + 1, total);
+            });
+        }
+    });
+
+    // A failed chunk's error wins over the chunks it stopped; the rest were not attempted because
+    // the request was cancelled, and a translation with holes in it is no answer
+    let mut translations = Vec::with_capacity(total);
+    for result in results.into_inner().unwrap() {
+        match result {
+            Some(Ok(translation)) => translations.push(Some(translation)),
+            Some(Err(error)) => return Err(error),
+            None => translations.push(None),
+        }
+    }
+    let Some(translations) = translations.into_iter().collect::<Option<Vec<_>>>() else {
+        return Err(TranslationError::Cancelled);
+    };
+
+    // Word details (dictionary, romanization) are per request and don't survive joining
+    let mut output = Translation::default();
+    for (chunk, translation) in chunks.iter().zip(translations) {
+        output.text.push_str(&chunk.lead);
+        output.text.push_str(translation.text.trim());
+        output.text.push_str(&chunk.trail);
+        if output.detected_source.is_none() {
+            output.detected_source = translation.detected_source;
+        }
+        for term in translation.glossary_terms {
+            if !output.glossary_terms.contains(&term) {
+                output.glossary_terms.push(term);
+            }
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::actions::CancelToken;
+
+    type Hook = Box<dyn Fn(&TranslationRequest) -> Option<TranslationError> + Send + Sync>;
+
+    // Uppercases its input, so the reassembled result can be compared with the original
+    struct Upper {
+        max_chunk_chars: usize,
+        // Called before each chunk is translated
+        on_translate: Hook,
+    }
+
+    impl Upper {
+        fn new(max_chunk_chars: usize) -> Self {
+            Self { max_chunk_chars, on_translate: Box::new(|_| None) }
+        }
+    }
+
+    impl Translator for Upper {
+        fn id(&self) -> &str {
+            "upper"
+        }
+
+        fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+            if let Some(error) = (self.on_translate)(request) {
+                return Err(error);
+            }
+            Ok(Translation { text: request.text.to_uppercase(), ..Translation::default() })
+        }
+
+        fn max_chunk_chars(&self) -> usize {
+            self.max_chunk_chars
+        }
+    }
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest {
+            text: text.to_string(),
+            source: None,
+            target: "en".to_string(),
+            bypass_cache: false,
+            glossary: Vec::new(),
+            cancel: CancelToken::new(),
+        }
+    }
+
+    fn bodies(chunks: &[Chunk]) -> Vec<&str> {
+        chunks.iter().map(|c| c.body.as_str()).collect()
+    }
+
+    #[test]
+    fn sentences_end_at_cjk_terminals_and_keep_their_closers() {
+        assert_eq!(sentence_units("晴れ。雨！本当？「はい。」次"), ["晴れ。", "雨！", "本当？", "「はい。」", "次"]);
+    }
+
+    #[test]
+    fn ascii_terminals_need_whitespace_after_them() {
+        assert_eq!(sentence_units("Hi. See example.com now!? Ok"), ["Hi.", " See example.com now!?", " Ok"]);
+        assert_eq!(sentence_units("one\ntwo"), ["one\n", "two"]);
+    }
+
+    #[test]
+    fn oversize_sentences_are_split_at_whitespace_then_anywhere() {
+        assert_eq!(bodies(&split_chunks("aaaa bbbb cccc", 10)), ["aaaa bbbb", "cccc"]);
+        assert_eq!(bodies(&split_chunks(&"あ".repeat(25), 10)), ["あ".repeat(10), "あ".repeat(10), "あ".repeat(5)]);
+    }
+
+    #[test]
+    fn chunks_keep_whitespace_at_their_edges() {
+        let text = "  First one. Second one.\n\n\tThird one.  ";
+        let chunks = split_chunks(text, 12);
+        // Whitespace-only pieces become empty chunks, which aren't sent to the provider
+        assert_eq!(bodies(&chunks), ["First one.", "Second one.", "", "Third one.", ""]);
+        let joined: String = chunks.iter().map(|c| format!("{}{}{}", c.lead, c.body, c.trail)).collect();
+        assert_eq!(joined, text);
+    }
+
+    #[test]
+    fn reassembles_in_order_with_the_original_whitespace() {
+        let text = "  first sentence. second one!\n\n\tthird, indented.\nfourth?  fifth one.\n";
+        let mut translator = Upper::new(12);
+        // Earlier chunks finish last
+        translator.on_translate = Box::new(|request| {
+            let delay = if request.text.starts_with("first") { 60 } else { 0 };
+            std::thread::sleep(Duration::from_millis(delay));
+            None
+        });
+        let translation = translate_chunked(&translator, &request(text), 4, &|_, _| {}).unwrap();
+        assert_eq!(translation.text, text.to_uppercase());
+    }
+
+    #[test]
+    fn cancelling_midway_is_an_error() {
+        let mut translator = Upper::new(10);
+        translator.on_translate = Box::new(|request| {
+            request.cancel.cancel();
+            None
+        });
+        let result = translate_chunked(&translator, &request("one two. three four. five six."), 1, &|_, _| {});
+        assert_eq!(result.unwrap_err(), TranslationError::Cancelled);
+    }
+
+    #[test]
+    fn a_failed_chunk_fails_the_whole() {
+        let mut translator = Upper::new(10);
+        translator.on_translate =
+            Box::new(|request| request.text.starts_with("three").then_some(TranslationError::Timeout));
+        let result = translate_chunked(&translator, &request("one two. three four. five six."), 1, &|_, _| {});
+        assert_eq!(result.unwrap_err(), TranslationError::Timeout);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::glossary::fingerprint;
+use super::{
+    join_url, send, unexpected, GlossaryEntry, Translation, TranslationError, TranslationRequest, TranslationResult,
+    Translator,
+};
+
+// DeepL API v2. Free-plan keys (ending in ":fx") use api-free.deepl.com, paid ones api.deepl.com.
+pub struct DeepLTranslator {
+    client: reqwest::blocking::Client,
+    url: String,
+    glossaries_url: String,
+    api_key: String,
+    // Glossary ids by name; names include a content hash, so an edited glossary gets a new one
+    glossary_ids: Mutex<HashMap<String, String>>,
+}
+
+impl DeepLTranslator {
+    pub fn new(client: reqwest::blocking::Client, base_url: &str, api_key: &str) -> Self {
+        Self {
+            client,
+            url: join_url(base_url, "v2/translate"),
+            glossaries_url: join_url(base_url, "v2/glossaries"),
+            api_key: api_key.to_string(),
+            glossary_ids: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn auth(&self) -> String {
+        format!("DeepL-Auth-Key {}", self.api_key)
+    }
+
+    // Find the glossary an earlier run uploaded, or upload it now
+    fn glossary_id(&self, source: &str, target: &str, entries: &[GlossaryEntry]) -> Result<String, TranslationError> {
+        let (source, target) = (primary_code(source), primary_code(target));
+        let name = format!("popwin {}-{} {}", source, target, fingerprint(entries));
+        if let Some(id) = self.glossary_ids.lock().unwrap().get(&name) {
+            return Ok(id.clone());
+        }
+
+        let list = send(self.client.get(&self.glossaries_url).header("Authorization", self.auth()))?
+            .json::<serde_json::Value>()?;
+        let existing = list["glossaries"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|g| g["name"].as_str() == Some(name.as_str()))
+            .and_then(|g| g["glossary_id"].as_str().map(|id| id.to_string()));
+
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                // TSV entries can't hold tabs or line breaks; such entries are skipped
+                let tsv = entries
+                    .iter()
+                    .filter(|e| ![&e.source, &e.target].iter().any(|t| t.contains(['\t', '\n', '\r'])))
+                    .map(|e| format!("{}\t{}", e.source, e.target))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let params = [
+                    ("name", name.as_str()),
+                    ("source_lang", source.as_str()),
+                    ("target_lang", target.as_str()),
+                    ("entries", tsv.as_str()),
+                    ("entries_format", "tsv"),
+                ];
+                let http = self.client.post(&self.glossaries_url).header("Authorization", self.auth()).form(&params);
+                let created = send(http)?.json::<serde_json::Value>()?;
+                created["glossary_id"]
+                    .as_str()
+                    .ok_or_else(|| unexpected("no glossary_id in response"))?
+                    .to_string()
+            }
+        };
+        self.glossary_ids.lock().unwrap().insert(name, id.clone());
+        Ok(id)
+    }
+}
+
+// Glossaries are per language, not per variant: "EN-US" → "en"
+fn primary_code(code: &str) -> String {
+    code.split(['-', '_']).next().unwrap_or(code).to_ascii_lowercase()
+}
+
+impl Translator for DeepLTranslator {
+    fn id(&self) -> &str {
+        "deepl"
+    }
+
+    // Well under the 128 KiB request limit, and small enough to parallelize
+    fn max_chunk_chars(&self) -> usize {
+        10_000
+    }
+
+    fn native_glossary(&self) -> bool {
+        true
+    }
+
+    fn fingerprint(&self) -> String {
+        self.url.clone()
+    }
+
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+        let target = request.target.to_ascii_uppercase();
+        let mut params = vec![("text", request.text.clone()), ("target_lang", target)];
+        if let Some(source) = &request.source {
+            params.push(("source_lang", source.to_ascii_uppercase()));
+            // Glossaries need an explicit source language
+            if !request.glossary.is_empty() {
+                match self.glossary_id(source, &request.target, &request.glossary) {
+                    Ok(id) => params.push(("glossary_id", id)),
+                    Err(e) => log::warn!("DeepL glossary unavailable, translating without it: {}", e),
+                }
+            }
+        }
+
+        let http = self.client.post(&self.url).header("Authorization", self.auth()).form(&params);
+        let resp = send(http)?;
+        let json = resp.json::<serde_json::Value>()?;
+
+        // {"translations": [{"detected_source_language": "EN", "text": "..."}]}
+        let translations = json["translations"]
+            .as_array()
+            .ok_or_else(|| unexpected("missing `translations` array"))?;
+        let result: String = translations.iter().filter_map(|t| t["text"].as_str()).collect();
+        if result.is_empty() {
+            return Err(unexpected("no translated text in response"));
+        }
+        let detected = translations
+            .first()
+            .and_then(|t| t["detected_source_language"].as_str())
+            .map(|lang| lang.to_ascii_lowercase());
+        Ok(Translation { detected_source: detected, ..Translation::plain(result) })
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranslationError {
+    Timeout,
+    // Could not reach the provider at all (no network, DNS, connection refused)
+    Offline(String),
+    RateLimited { retry_after: Option<Duration> },
+    Http { status: u16, body: String },
+    // The provider answered, but not in the shape we expected
+    Parse(String),
+    // Provider is misconfigured (missing key, bad URL)
+    Config(String),
+    // The local engine ran but failed
+    Engine(String),
+    // The request's cancel token fired before it finished
+    Cancelled,
+}
+
+pub type TranslationResult = Result<super::Translation, TranslationError>;
+
+impl TranslationError {
+    // Short message for the toolbar; the Display impl has the detail
+    pub fn summary(&self) -> String {
+        match self {
+            TranslationError::Timeout => "タイムアウトしました".to_string(),
+            TranslationError::Offline(_) => "通信エラー (オフライン?)".to_string(),
+            TranslationError::RateLimited { .. } => "リクエスト制限中です".to_string(),
+            TranslationError::Http { status, .. } => format!("翻訳エラー (HTTP {})", status),
+            TranslationError::Parse(_) => "翻訳エラー (応答を解析できません)".to_string(),
+            TranslationError::Config(_) => "設定エラー".to_string(),
+            TranslationError::Engine(_) => "ローカル翻訳エラー".to_string(),
+            TranslationError::Cancelled => "キャンセルされました".to_string(),
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, TranslationError::Config(_) | TranslationError::Cancelled)
+    }
+}
+
+impl fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranslationError::Timeout => write!(f, "request timed out"),
+            TranslationError::Offline(detail) => write!(f, "connection failed: {}", detail),
+            TranslationError::RateLimited { retry_after: Some(after) } => {
+                write!(f, "rate limited, retry after {}s", after.as_secs())
+            }
+            TranslationError::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            TranslationError::Http { status, body } if body.is_empty() => write!(f, "HTTP {}", status),
+            TranslationError::Http { status, body } => write!(f, "HTTP {}: {}", status, body),
+            TranslationError::Parse(detail) => write!(f, "unexpected response: {}", detail),
+            TranslationError::Config(detail) => write!(f, "{}", detail),
+            TranslationError::Engine(detail) => write!(f, "local engine: {}", detail),
+            TranslationError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for TranslationError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            TranslationError::Timeout
+        } else if e.is_decode() {
+            TranslationError::Parse(e.to_string())
+        } else if e.is_builder() {
+            TranslationError::Config(e.to_string())
+        } else {
+            TranslationError::Offline(e.to_string())
+        }
+    }
+}
@@ -0,0 +1,341 @@
+// Team glossary: terms that must be translated a fixed way (or not at all).
+//
+// A CSV or TSV file whose header names the language of each column; any two columns
+// form a language pair:
+//
+//   en,ja
+//   PopWin,PopWin
+//   workspace,ワークスペース
+//
+// Matched terms are replaced by placeholders before the text is sent and swapped for the
+// target term afterwards. Providers with native glossaries (DeepL) get the terms instead.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use super::{detect_language_among, same_language, Translation, TranslationRequest, TranslationResult, Translator};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlossaryEntry {
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(Debug)]
+pub struct Glossary {
+    languages: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Glossary {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("cannot read file: {}", e))?;
+        let delimiter = if path.extension().and_then(|e| e.to_str()) == Some("tsv") { '\t' } else { ',' };
+
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'));
+        let (_, header) = lines.next().ok_or("glossary is empty")?;
+        let languages: Vec<String> = split_row(header, delimiter).iter().map(|l| l.trim().to_string()).collect();
+        if languages.len() < 2 || languages.iter().any(|l| l.is_empty()) {
+            return Err("the first line must name two or more languages, e.g. `en,ja`".to_string());
+        }
+
+        let mut rows = Vec::new();
+        for (index, line) in lines {
+            let row = split_row(line, delimiter);
+            if row.len() > languages.len() {
+                return Err(format!("line {}: {} columns, but the header has {}", index + 1, row.len(), languages.len()));
+            }
+            rows.push(row.into_iter().map(|cell| cell.trim().to_string()).collect());
+        }
+        Ok(Self { languages, rows })
+    }
+
+    // Every pair for source → target, longest source term first so overlapping terms resolve sensibly
+    pub fn entries(&self, source: &str, target: &str) -> Vec<GlossaryEntry> {
+        let column = |lang: &str| self.languages.iter().position(|l| same_language(l, lang));
+        let (Some(from), Some(to)) = (column(source), column(target)) else {
+            return Vec::new();
+        };
+        if from == to {
+            return Vec::new();
+        }
+        let mut entries: Vec<GlossaryEntry> = Vec::new();
+        for row in &self.rows {
+            let (Some(source), Some(target)) = (row.get(from), row.get(to)) else { continue };
+            if source.is_empty() || target.is_empty() || entries.iter().any(|e| &e.source == source) {
+                continue;
+            }
+            entries.push(GlossaryEntry { source: source.clone(), target: target.clone() });
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.source.chars().count()));
+        entries
+    }
+}
+
+// Comma rows may quote fields ("a, b" and "" for a literal quote); tab rows are split as-is
+fn split_row(line: &str, delimiter: char) -> Vec<String> {
+    if delimiter == '\t' {
+        return line.split('\t').map(|s| s.to_string()).collect();
+    }
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells
+}
+
+// Where `term` occurs in `text`. ASCII terms only match whole words, so "app" leaves "apple" alone.
+fn find_term(text: &str, term: &str) -> Vec<usize> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    text.match_indices(term)
+        .filter(|(start, _)| {
+            let before = text[..*start].chars().next_back();
+            let after = text[start + term.len()..].chars().next();
+            let edge_ok = |neighbour: Option<char>, edge: Option<char>| match (neighbour, edge) {
+                (Some(n), Some(e)) => !(is_word(n) && is_word(e)),
+                _ => true,
+            };
+            edge_ok(before, term.chars().next()) && edge_ok(after, term.chars().next_back())
+        })
+        .map(|(start, _)| start)
+        .collect()
+}
+
+// Providers rarely touch "[[3]]" but may widen the brackets or add spaces
+fn placeholder(index: usize) -> String {
+    format!("[[{}]]", index)
+}
+
+fn placeholder_pattern() -> Regex {
+    Regex::new(r"[\[［]{2}\s*(\d+)\s*[\]］]{2}").expect("valid placeholder regex")
+}
+
+// Text with glossary terms swapped for numbered placeholders
+struct Masked {
+    text: String,
+    // What each placeholder turns back into
+    replacements: Vec<String>,
+    // Entries that matched, in placeholder order
+    used: Vec<GlossaryEntry>,
+}
+
+// Terms are found in the original text in one pass, longest first (see `Glossary::entries`),
+// so a term inside a longer one or inside a placeholder is left alone. Placeholder-like text
+// the user wrote is masked too, so it comes back verbatim instead of as a glossary term.
+fn mask(text: &str, entries: &[GlossaryEntry]) -> Masked {
+    let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+    let mut replacements: Vec<String> = Vec::new();
+    let mut used = Vec::new();
+    for found in placeholder_pattern().find_iter(text) {
+        let index = match replacements.iter().position(|r| r == found.as_str()) {
+            Some(index) => index,
+            None => {
+                replacements.push(found.as_str().to_string());
+                replacements.len() - 1
+            }
+        };
+        spans.push((found.start(), found.end(), index));
+    }
+    for entry in entries {
+        let free: Vec<usize> = find_term(text, &entry.source)
+            .into_iter()
+            .filter(|&start| spans.iter().all(|&(s, e, _)| start + entry.source.len() <= s || e <= start))
+            .collect();
+        if free.is_empty() {
+            continue;
+        }
+        let index = replacements.len();
+        replacements.push(entry.target.clone());
+        used.push(entry.clone());
+        spans.extend(free.into_iter().map(|start| (start, start + entry.source.len(), index)));
+    }
+
+    spans.sort_unstable();
+    let mut masked = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, end, index) in spans {
+        masked.push_str(&text[copied..start]);
+        masked.push_str(&placeholder(index));
+        copied = end;
+    }
+    masked.push_str(&text[copied..]);
+    Masked { text: masked, replacements, used }
+}
+
+// Swap the placeholders back; false when one went missing or an unknown one turned up
+fn unmask(text: &str, replacements: &[String]) -> (String, bool) {
+    let mut seen = vec![false; replacements.len()];
+    let mut unknown = false;
+    let text = placeholder_pattern()
+        .replace_all(text, |caps: &regex::Captures| {
+            let index: usize = caps[1].parse().unwrap_or(usize::MAX);
+            match replacements.get(index) {
+                Some(replacement) => {
+                    seen[index] = true;
+                    replacement.clone()
+                }
+                None => {
+                    unknown = true;
+                    caps[0].to_string()
+                }
+            }
+        })
+        .into_owned();
+    (text, !unknown && seen.iter().all(|&s| s))
+}
+
+// Stable short hash for naming native glossaries and keying the cache
+pub fn fingerprint(entries: &[GlossaryEntry]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for entry in entries {
+        for byte in entry.source.bytes().chain([0]).chain(entry.target.bytes()).chain([1]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+// Applies the glossary around any provider
+pub struct GlossaryTranslator {
+    inner: Arc<dyn Translator>,
+    glossary: Arc<Glossary>,
+}
+
+impl GlossaryTranslator {
+    pub fn new(inner: Arc<dyn Translator>, glossary: Arc<Glossary>) -> Self {
+        Self { inner, glossary }
+    }
+}
+
+impl Translator for GlossaryTranslator {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+        // Without a source language we can't pick the column; script detection is good enough here
+        let source =
+            request.source.as_deref().or_else(|| detect_language_among(&request.text, &self.glossary.languages));
+        let entries = match source {
+            Some(source) => self.glossary.entries(source, &request.target),
+            None => Vec::new(),
+        };
+        let masked = mask(&request.text, &entries);
+        if masked.used.is_empty() {
+            return self.inner.translate(request);
+        }
+        let glossary_terms = masked.used.iter().map(|e| e.target.clone()).collect();
+
+        // A native glossary pins the source language, so only when the user configured one;
+        // a script guess would make DeepL read German or French as English
+        if self.inner.native_glossary() && request.source.is_some() {
+            let native = TranslationRequest { glossary: entries.clone(), ..request.clone() };
+            let translation = self.inner.translate(&native)?;
+            return Ok(Translation { glossary_terms, ..translation });
+        }
+
+        let translation = self.inner.translate(&TranslationRequest { text: masked.text, ..request.clone() })?;
+        let (text, intact) = unmask(&translation.text, &masked.replacements);
+        if !intact {
+            log::warn!("{} dropped or altered glossary placeholders", self.inner.id());
+        }
+        Ok(Translation { text, glossary_terms, ..translation })
+    }
+
+    fn max_chunk_chars(&self) -> usize {
+        self.inner.max_chunk_chars()
+    }
+
+    fn fingerprint(&self) -> String {
+        self.inner.fingerprint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(source: &str, target: &str) -> GlossaryEntry {
+        GlossaryEntry { source: source.to_string(), target: target.to_string() }
+    }
+
+    // What the translation would be if the provider left the placeholders alone
+    fn round_trip(text: &str, entries: &[GlossaryEntry]) -> String {
+        let masked = mask(text, entries);
+        let (text, intact) = unmask(&masked.text, &masked.replacements);
+        assert!(intact, "{}", masked.text);
+        text
+    }
+
+    #[test]
+    fn terms_round_trip_as_their_targets() {
+        let entries = [entry("workspace", "ワークスペース"), entry("PopWin", "PopWin")];
+        let masked = mask("Open the workspace in PopWin, then the workspace again", &entries);
+        assert_eq!(masked.text, "Open the [[0]] in [[1]], then the [[0]] again");
+        assert_eq!(masked.used, entries);
+        assert_eq!(round_trip("Open the workspace in PopWin", &entries), "Open the ワークスペース in PopWin");
+    }
+
+    #[test]
+    fn longest_term_wins_and_shorter_terms_inside_it_are_left_alone() {
+        let glossary = Glossary {
+            languages: vec!["ja".to_string(), "en".to_string()],
+            rows: vec![
+                vec!["東京".to_string(), "Tokyo".to_string()],
+                vec!["東京都".to_string(), "Tokyo Metropolis".to_string()],
+            ],
+        };
+        let entries = glossary.entries("ja", "en");
+        assert_eq!(entries[0].source, "東京都");
+        let masked = mask("東京都と東京", &entries);
+        assert_eq!(masked.text, "[[0]]と[[1]]");
+        assert_eq!(round_trip("東京都と東京", &entries), "Tokyo MetropolisとTokyo");
+    }
+
+    #[test]
+    fn terms_match_case_and_whole_words() {
+        let entries = [entry("PopWin", "ポップウィン"), entry("app", "アプリ")];
+        assert_eq!(round_trip("popwin, PopWin, apple, app", &entries), "popwin, ポップウィン, apple, アプリ");
+    }
+
+    #[test]
+    fn placeholder_like_text_from_the_user_comes_back_verbatim() {
+        let entries = [entry("workspace", "ワークスペース")];
+        assert_eq!(
+            round_trip("see [[0]] and [[1]] in the workspace", &entries),
+            "see [[0]] and [[1]] in the ワークスペース"
+        );
+    }
+
+    #[test]
+    fn altered_placeholders_are_reported() {
+        let masked = mask("the workspace", &[entry("workspace", "ワークスペース")]);
+        assert_eq!(unmask("the ［［ 0 ］］", &masked.replacements), ("the ワークスペース".to_string(), true));
+        assert!(!unmask("the", &masked.replacements).1);
+        assert!(!unmask("the [[0]] [[7]]", &masked.replacements).1);
+    }
+
+    #[test]
+    fn rows_split_on_tabs_as_is_and_on_quoted_commas() {
+        assert_eq!(split_row("a\t\"b\"\t", '\t'), ["a", "\"b\"", ""]);
+        assert_eq!(split_row(r#"a,"b, c","say ""hi""""#, ','), ["a", "b, c", r#"say "hi""#]);
+    }
+}
@@ -0,0 +1,118 @@
+use serde_json::Value;
+
+use super::{join_url, send, unexpected, DictionaryEntry, Translation, TranslationRequest, TranslationResult, Translator};
+
+// Unofficial `gtx` endpoint used by the browser extension; no key required
+pub struct GoogleTranslator {
+    client: reqwest::blocking::Client,
+    url: String,
+}
+
+impl GoogleTranslator {
+    pub fn new(client: reqwest::blocking::Client, base_url: &str) -> Self {
+        Self {
+            client,
+            url: join_url(base_url, "translate_a/single"),
+        }
+    }
+}
+
+impl Translator for GoogleTranslator {
+    fn id(&self) -> &str {
+        "google"
+    }
+
+    // The endpoint refuses anything longer, even over POST
+    fn max_chunk_chars(&self) -> usize {
+        5000
+    }
+
+    fn fingerprint(&self) -> String {
+        self.url.clone()
+    }
+
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+        let mut params = vec![
+            ("client", "gtx"),
+            ("sl", request.source.as_deref().unwrap_or("auto")),
+            ("tl", request.target.as_str()),
+            ("dt", "t"),
+            ("dt", "rm"),
+        ];
+        if is_lookup(&request.text) {
+            params.extend([("dt", "bd"), ("dt", "at")]);
+        }
+
+        // The text goes in a form body; as a query parameter it overflows URL limits
+        let http = self.client.post(&self.url).query(&params).form(&[("q", request.text.as_str())]);
+        let resp = send(http)?;
+        let json = resp.json::<serde_json::Value>()?;
+
+        let mut translation = parse_response(&json);
+        if translation.text.is_empty() {
+            return Err(unexpected("no translated text in response"));
+        }
+        if request.source.is_some() {
+            translation.detected_source = None;
+        }
+        Ok(translation)
+    }
+}
+
+// Dictionary and alternatives only make sense for a word or short phrase
+fn is_lookup(text: &str) -> bool {
+    let text = text.trim();
+    !text.contains('\n') && text.chars().count() <= 40 && text.split_whitespace().count() <= 3
+}
+
+// Response layout (positional arrays, missing parts are null):
+//   [0] sentences: [["訳", "original", ...], ..., [null, null, "translit", "source translit"]]
+//   [1] dictionary: [["noun", ["term", ...], ...], ...]
+//   [2] detected source language
+//   [5] alternatives per segment: [["segment", null, [["alternative", score, ...], ...], ...], ...]
+fn parse_response(json: &Value) -> Translation {
+    let mut translation = Translation::default();
+
+    for sentence in json[0].as_array().into_iter().flatten() {
+        match sentence[0].as_str() {
+            Some(text) => translation.text.push_str(text),
+            None => {
+                translation.romanization = non_empty(&sentence[2]);
+                translation.source_romanization = non_empty(&sentence[3]);
+            }
+        }
+    }
+
+    for entry in json[1].as_array().into_iter().flatten() {
+        let terms: Vec<String> = entry[1]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t.as_str().map(|s| s.to_string()))
+            .collect();
+        if !terms.is_empty() {
+            translation.dictionary.push(DictionaryEntry {
+                part_of_speech: entry[0].as_str().unwrap_or_default().to_string(),
+                terms,
+            });
+        }
+    }
+
+    translation.detected_source = non_empty(&json[2]);
+
+    // Alternatives are per segment; only a single segment (a word or phrase) is worth showing
+    if let Some([segment]) = json[5].as_array().map(|a| a.as_slice()) {
+        for alternative in segment[2].as_array().into_iter().flatten() {
+            if let Some(text) = alternative[0].as_str() {
+                if text != translation.text && !translation.alternatives.iter().any(|a| a == text) {
+                    translation.alternatives.push(text.to_string());
+                }
+            }
+        }
+    }
+    translation
+}
+
+fn non_empty(value: &Value) -> Option<String> {
+    value.as_str().map(|s| s.trim()).filter(|s| !s.is_empty()).map(|s| s.to_string())
+}
@@ -0,0 +1,85 @@
+// Script-based language guess. It runs locally and instantly, which is all the
+// auto-swap decision needs: kana means Japanese, hangul Korean, and so on.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let (mut kana, mut han, mut hangul, mut latin, mut cyrillic) = (0, 0, 0, 0, 0);
+    for c in text.chars() {
+        match c as u32 {
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => kana += 1,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => han += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => hangul += 1,
+            0x0400..=0x04FF => cyrillic += 1,
+            _ if c.is_ascii_alphabetic() => latin += 1,
+            _ => {}
+        }
+    }
+
+    let cjk = kana + han + hangul;
+    if kana > 0 && kana + han >= hangul {
+        return Some("ja");
+    }
+    if hangul > 0 && hangul >= han {
+        return Some("ko");
+    }
+    if han > 0 && han >= latin {
+        return Some("zh");
+    }
+    if cyrillic > latin && cyrillic > cjk {
+        return Some("ru");
+    }
+    if latin > 0 {
+        // Latin script is ambiguous (en/de/fr...); English is the useful default here
+        return Some("en");
+    }
+    None
+}
+
+// `detect_language` for a setup that only expects `languages`. Text in kanji alone ("会議室予約")
+// reads as Chinese by script, so it is taken for Japanese when Chinese isn't one of them.
+pub fn detect_language_among<S: AsRef<str>>(text: &str, languages: &[S]) -> Option<&'static str> {
+    let detected = detect_language(text)?;
+    let expects = |code: &str| languages.iter().any(|l| same_language(l.as_ref(), code));
+    if detected == "zh" && expects("ja") && !expects("zh") {
+        return Some("ja");
+    }
+    Some(detected)
+}
+
+// Codes are compared loosely: "JA", "ja" and "ja-JP" are the same language here
+pub fn same_language(a: &str, b: &str) -> bool {
+    let primary = |code: &str| code.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+    primary(a) == primary(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_map_to_languages() {
+        assert_eq!(detect_language("これはペンです"), Some("ja"));
+        assert_eq!(detect_language("会議室を予約しました"), Some("ja"));
+        assert_eq!(detect_language("안녕하세요"), Some("ko"));
+        assert_eq!(detect_language("我们今天开会"), Some("zh"));
+        assert_eq!(detect_language("Привет, мир"), Some("ru"));
+        assert_eq!(detect_language("Hello, world"), Some("en"));
+        assert_eq!(detect_language("12:30 — ★"), None);
+    }
+
+    #[test]
+    fn kanji_only_text_is_japanese_unless_chinese_is_expected() {
+        for text in ["会議室予約", "東京都"] {
+            assert_eq!(detect_language(text), Some("zh"));
+            assert_eq!(detect_language_among(text, &["ja", "en"]), Some("ja"), "{}", text);
+            assert_eq!(detect_language_among(text, &["ja-JP", "zh-CN"]), Some("zh"), "{}", text);
+            assert_eq!(detect_language_among(text, &["en", "de"]), Some("zh"), "{}", text);
+        }
+        assert_eq!(detect_language_among("Hello", &["ja"]), Some("en"));
+    }
+
+    #[test]
+    fn codes_compare_by_primary_language() {
+        assert!(same_language("JA", "ja-JP"));
+        assert!(same_language("zh_TW", "zh"));
+        assert!(!same_language("en", "ja"));
+    }
+}
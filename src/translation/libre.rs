@@ -0,0 +1,57 @@
+use serde_json::json;
+
+use super::{join_url, send, unexpected, Translation, TranslationRequest, TranslationResult, Translator};
+
+// LibreTranslate, usually self-hosted (http://localhost:5000 by default)
+pub struct LibreTranslator {
+    client: reqwest::blocking::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl LibreTranslator {
+    pub fn new(client: reqwest::blocking::Client, base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client,
+            url: join_url(base_url, "translate"),
+            api_key,
+        }
+    }
+}
+
+impl Translator for LibreTranslator {
+    fn id(&self) -> &str {
+        "libretranslate"
+    }
+
+    // Public instances reject requests over 2000 characters
+    fn max_chunk_chars(&self) -> usize {
+        2000
+    }
+
+    fn fingerprint(&self) -> String {
+        self.url.clone()
+    }
+
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+        let mut body = json!({
+            "q": request.text,
+            "source": request.source.as_deref().unwrap_or("auto"),
+            "target": request.target,
+            "format": "text",
+        });
+        if let Some(key) = &self.api_key {
+            body["api_key"] = json!(key);
+        }
+
+        let resp = send(self.client.post(&self.url).json(&body))?;
+        let json = resp.json::<serde_json::Value>()?;
+
+        // {"translatedText": "...", "detectedLanguage": {"language": "en"}} (the latter only for source=auto)
+        let text = json["translatedText"].as_str().ok_or_else(|| unexpected("no translated text in response"))?;
+        Ok(Translation {
+            detected_source: json["detectedLanguage"]["language"].as_str().map(|s| s.to_string()),
+            ..Translation::plain(text)
+        })
+    }
+}
@@ -0,0 +1,137 @@
+// Offline translation with a local CPU model, for machines without network access.
+//
+// Models live in one directory per language pair under `models_dir` (en-ja/, ja-en/, ...).
+// The engine is an external command that reads one sentence per line on stdin and writes
+// one translation per line on stdout, e.g. marian-decoder for Marian/Bergamot models or a
+// small CTranslate2 script. `{model}` in the command is replaced by the pair's directory.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::chunk::sentence_units;
+use super::{detect_language_among, Translation, TranslationError, TranslationRequest, TranslationResult, Translator};
+use crate::actions::{run_process, ProcessError, ProcessSpec};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocalConfig {
+    pub models_dir: Option<PathBuf>,
+    pub command: String,
+    // Covers loading the model, which dominates for short selections
+    pub timeout_secs: u64,
+}
+
+impl Default for LocalConfig {
+    fn default() -> Self {
+        Self {
+            models_dir: None,
+            command: "marian-decoder -c {model}/decoder.yml --cpu-threads 4 --quiet".to_string(),
+            timeout_secs: 60,
+        }
+    }
+}
+
+pub struct LocalTranslator {
+    models_dir: PathBuf,
+    command: String,
+    timeout: Duration,
+}
+
+impl LocalTranslator {
+    pub fn new(config: &LocalConfig) -> Result<Self, String> {
+        let models_dir = config.models_dir.clone().ok_or("the local provider needs translation.local.models_dir")?;
+        if !models_dir.is_dir() {
+            return Err(format!("translation.local.models_dir {} is not a directory", models_dir.display()));
+        }
+        if !config.command.contains("{model}") {
+            return Err("translation.local.command must contain {model}".to_string());
+        }
+        Ok(Self {
+            models_dir,
+            command: config.command.clone(),
+            timeout: Duration::from_secs(config.timeout_secs.max(1)),
+        })
+    }
+
+    fn model_dir(&self, source: &str, target: &str) -> Option<PathBuf> {
+        let primary = |code: &str| code.split(['-', '_']).next().unwrap_or(code).to_ascii_lowercase();
+        [format!("{}-{}", source, target), format!("{}-{}", primary(source), primary(target))]
+            .into_iter()
+            .map(|name| self.models_dir.join(name.to_ascii_lowercase()))
+            .find(|dir| dir.is_dir())
+    }
+}
+
+impl Translator for LocalTranslator {
+    fn id(&self) -> &str {
+        "local"
+    }
+
+    // Each request starts the engine and loads the model, so fewer, larger chunks are cheaper
+    fn max_chunk_chars(&self) -> usize {
+        20_000
+    }
+
+    fn fingerprint(&self) -> String {
+        format!("{} {}", self.command, self.models_dir.display())
+    }
+
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+        // Kanji-only text goes to the Japanese model when there is no Chinese one
+        let installed: Vec<&str> =
+            ["ja", "zh"].into_iter().filter(|lang| self.model_dir(lang, &request.target).is_some()).collect();
+        let source = request
+            .source
+            .as_deref()
+            .or_else(|| detect_language_among(&request.text, &installed))
+            .ok_or_else(|| TranslationError::Config("cannot tell the source language; set translation.source".to_string()))?;
+        let model = self.model_dir(source, &request.target).ok_or_else(|| {
+            TranslationError::Config(format!(
+                "no local model for {}→{} in {}",
+                source,
+                request.target,
+                self.models_dir.display()
+            ))
+        })?;
+
+        // One sentence per input line; the whitespace between sentences is put back afterwards
+        let units: Vec<(&str, &str, &str)> = sentence_units(&request.text)
+            .into_iter()
+            .map(|unit| {
+                let body = unit.trim();
+                let lead = &unit[..unit.len() - unit.trim_start().len()];
+                (lead, body, &unit[lead.len() + body.len()..])
+            })
+            .collect();
+        let input: String = units
+            .iter()
+            .filter(|(_, body, _)| !body.is_empty())
+            .map(|(_, body, _)| format!("{}\n", body))
+            .collect();
+
+        let command = self.command.replace("{model}", &format!("\"{}\"", model.display()));
+        let spec = ProcessSpec { timeout: self.timeout, ..ProcessSpec::new(command) };
+        let output = run_process(&spec, &input, &request.cancel).map_err(|e| match e {
+            ProcessError::Timeout(_) => TranslationError::Timeout,
+            ProcessError::Cancelled => TranslationError::Cancelled,
+            ProcessError::Spawn(e) => TranslationError::Config(format!("cannot start the local engine: {}", e)),
+            e => TranslationError::Engine(e.to_string()),
+        })?;
+
+        let mut lines = output.stdout.lines();
+        let mut text = String::new();
+        for (lead, body, trail) in units {
+            text.push_str(lead);
+            if !body.is_empty() {
+                let line = lines
+                    .next()
+                    .ok_or_else(|| TranslationError::Parse("the local engine returned fewer lines than it was given".to_string()))?;
+                text.push_str(line.trim());
+            }
+            text.push_str(trail);
+        }
+        Ok(Translation::plain(text))
+    }
+}
@@ -0,0 +1,404 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::actions::CancelToken;
+
+mod cache;
+mod chunk;
+mod deepl;
+mod error;
+mod glossary;
+mod google;
+mod lang;
+mod libre;
+mod local;
+mod openai;
+mod roundtrip;
+#[cfg(test)]
+mod tests;
+
+pub use cache::{CacheConfig, CachedTranslator, TranslationCache};
+pub use chunk::translate_chunked;
+pub use deepl::DeepLTranslator;
+pub use error::{TranslationError, TranslationResult};
+pub use glossary::{Glossary, GlossaryEntry, GlossaryTranslator};
+pub use google::GoogleTranslator;
+pub use lang::{detect_language, detect_language_among, same_language};
+pub use libre::LibreTranslator;
+pub use local::{LocalConfig, LocalTranslator};
+pub use openai::OpenAiTranslator;
+pub use roundtrip::RoundTrip;
+
+#[derive(Debug, Clone)]
+pub struct TranslationRequest {
+    pub text: String,
+    // None lets the provider detect the source language
+    pub source: Option<String>,
+    pub target: String,
+    // Skip cached results (the entry is still refreshed)
+    pub bypass_cache: bool,
+    // Glossary for the language pair, set only for providers with native glossary support
+    pub glossary: Vec<GlossaryEntry>,
+    // The toolbar's token for this request; chunking stops on it and the local engine is killed
+    pub cancel: CancelToken,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Translation {
+    pub text: String,
+    // Source language the provider detected, when it reports one
+    pub detected_source: Option<String>,
+    // Transliteration of the original and of the translation (e.g. romaji, pinyin)
+    pub source_romanization: Option<String>,
+    pub romanization: Option<String>,
+    // Dictionary entries and alternatives; only Google fills these, and only for short text
+    pub dictionary: Vec<DictionaryEntry>,
+    pub alternatives: Vec<String>,
+    // Target terms the glossary enforced, highlighted in the result view
+    pub glossary_terms: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DictionaryEntry {
+    pub part_of_speech: String,
+    pub terms: Vec<String>,
+}
+
+impl Translation {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self { text: text.into(), ..Self::default() }
+    }
+
+    pub fn has_details(&self) -> bool {
+        self.detected_source.is_some()
+            || self.source_romanization.is_some()
+            || self.romanization.is_some()
+            || !self.dictionary.is_empty()
+            || !self.alternatives.is_empty()
+    }
+}
+
+pub trait Translator: Send + Sync {
+    fn id(&self) -> &str;
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult;
+
+    // Longer selections are split into chunks of at most this many characters
+    fn max_chunk_chars(&self) -> usize {
+        4000
+    }
+
+    // Takes `TranslationRequest::glossary` itself instead of having terms masked
+    fn native_glossary(&self) -> bool {
+        false
+    }
+
+    // Settings that change the result (endpoint, model), so the cache doesn't serve one
+    // configuration's translations to another under the same id
+    fn fingerprint(&self) -> String {
+        String::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    Google,
+    DeepL,
+    LibreTranslate,
+    OpenAi,
+    Local,
+}
+
+impl ProviderKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ProviderKind::Google => "Google",
+            ProviderKind::DeepL => "DeepL",
+            ProviderKind::LibreTranslate => "LibreTranslate",
+            ProviderKind::OpenAi => "LLM",
+            ProviderKind::Local => "Local",
+        }
+    }
+}
+
+// Every provider has a base URL so it can be pointed at a self-hosted instance or a local mock
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderConfig {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    // Chat model for OpenAI-compatible endpoints
+    pub model: Option<String>,
+}
+
+impl ProviderConfig {
+    fn with_base_url(base_url: &str) -> Self {
+        Self { base_url: base_url.to_string(), api_key: None, model: None }
+    }
+
+    // The key from config, or the provider's usual environment variable
+    fn api_key_or_env(&self, var: &str) -> Option<String> {
+        self.api_key
+            .clone()
+            .or_else(|| std::env::var(var).ok())
+            .filter(|k| !k.trim().is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranslationConfig {
+    pub provider: ProviderKind,
+    // Source language code; unset lets the provider detect it
+    pub source: Option<String>,
+    // Default target language
+    pub target: String,
+    // Targets offered in the toolbar's language menu
+    pub languages: Vec<String>,
+    // Text already in `target` (e.g. Japanese) is translated to `swap_target` instead
+    pub auto_swap: bool,
+    pub swap_target: String,
+    // Per-request HTTP timeout
+    pub timeout_secs: u64,
+    // Chunks of a long selection translated at the same time
+    pub max_parallel: usize,
+    // Providers for the comparison button; empty means Google, the main provider and
+    // every provider that is set up (API key, model or local models)
+    pub compare: Vec<ProviderKind>,
+    // CSV/TSV glossary; defaults to glossary.tsv or glossary.csv in the config directory
+    pub glossary: Option<PathBuf>,
+    pub cache: CacheConfig,
+    pub google: ProviderConfig,
+    pub deepl: ProviderConfig,
+    pub libretranslate: ProviderConfig,
+    pub openai: ProviderConfig,
+    pub local: LocalConfig,
+}
+
+impl Default for TranslationConfig {
+    fn default() -> Self {
+        Self {
+            provider: ProviderKind::default(),
+            source: None,
+            target: "ja".to_string(),
+            languages: vec!["ja".to_string(), "en".to_string()],
+            auto_swap: false,
+            swap_target: "en".to_string(),
+            timeout_secs: 15,
+            max_parallel: 3,
+            compare: Vec::new(),
+            glossary: None,
+            cache: CacheConfig::default(),
+            google: ProviderConfig::with_base_url("https://translate.googleapis.com"),
+            deepl: ProviderConfig::with_base_url("https://api-free.deepl.com"),
+            libretranslate: ProviderConfig::with_base_url("http://localhost:5000"),
+            openai: ProviderConfig::with_base_url("https://api.openai.com/v1"),
+            local: LocalConfig::default(),
+        }
+    }
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self::with_base_url("")
+    }
+}
+
+impl TranslationConfig {
+    // Target for this text when the user didn't pick one explicitly
+    pub fn resolve_target(&self, text: &str) -> String {
+        if self.auto_swap {
+            let mut expected = self.languages.clone();
+            expected.extend([self.target.clone(), self.swap_target.clone()]);
+            expected.extend(self.source.clone());
+            if let Some(detected) = detect_language_among(text, &expected) {
+                if same_language(detected, &self.target) {
+                    return self.swap_target.clone();
+                }
+            }
+        }
+        self.target.clone()
+    }
+
+    pub fn compare_providers(&self) -> Vec<ProviderKind> {
+        let candidates = if self.compare.is_empty() {
+            let mut kinds = vec![ProviderKind::Google, self.provider];
+            if self.deepl.api_key_or_env("DEEPL_API_KEY").is_some() {
+                kinds.push(ProviderKind::DeepL);
+            }
+            // A local OpenAI-compatible server needs no key, but then a model is set explicitly
+            if self.openai.api_key_or_env("OPENAI_API_KEY").is_some() || self.openai.model.is_some() {
+                kinds.push(ProviderKind::OpenAi);
+            }
+            if self.local.models_dir.is_some() {
+                kinds.push(ProviderKind::Local);
+            }
+            kinds
+        } else {
+            self.compare.clone()
+        };
+        let mut providers = Vec::new();
+        for kind in candidates {
+            if !providers.contains(&kind) {
+                providers.push(kind);
+            }
+        }
+        providers
+    }
+
+    pub fn request(&self, text: &str, target: Option<&str>) -> TranslationRequest {
+        TranslationRequest {
+            text: text.to_string(),
+            source: self.source.clone().filter(|s| s != "auto"),
+            target: target.map(|t| t.to_string()).unwrap_or_else(|| self.resolve_target(text)),
+            bypass_cache: false,
+            glossary: Vec::new(),
+            cancel: CancelToken::new(),
+        }
+    }
+}
+
+// Build the configured provider. Misconfiguration (e.g. a missing API key) is returned
+// as an error so the caller can report it and fall back to Google.
+pub fn from_config(config: &TranslationConfig) -> Result<Arc<dyn Translator>, String> {
+    build(config.provider, config)
+}
+
+pub fn build(kind: ProviderKind, config: &TranslationConfig) -> Result<Arc<dyn Translator>, String> {
+    let client = http_client(Duration::from_secs(config.timeout_secs.max(1)));
+    let translator: Arc<dyn Translator> = match kind {
+        ProviderKind::Google => Arc::new(GoogleTranslator::new(client, &config.google.base_url)),
+        ProviderKind::DeepL => {
+            let key = config
+                .deepl
+                .api_key_or_env("DEEPL_API_KEY")
+                .ok_or("DeepL needs translation.deepl.api_key or $DEEPL_API_KEY")?;
+            Arc::new(DeepLTranslator::new(client, &config.deepl.base_url, &key))
+        }
+        ProviderKind::LibreTranslate => Arc::new(LibreTranslator::new(
+            client,
+            &config.libretranslate.base_url,
+            config.libretranslate.api_key_or_env("LIBRETRANSLATE_API_KEY"),
+        )),
+        ProviderKind::OpenAi => Arc::new(OpenAiTranslator::new(
+            client,
+            &config.openai.base_url,
+            config.openai.api_key_or_env("OPENAI_API_KEY"),
+            config.openai.model.as_deref().unwrap_or("gpt-4o-mini"),
+        )),
+        ProviderKind::Local => Arc::new(LocalTranslator::new(&config.local)?),
+    };
+    Ok(translator)
+}
+
+// Open the cache shared by all providers. If the cache file can't be opened the
+// cache runs in memory only and the problem is returned for the settings view.
+pub fn open_cache(config: &CacheConfig) -> (Option<Arc<TranslationCache>>, Option<String>) {
+    if !config.enabled {
+        return (None, None);
+    }
+    let path = config
+        .path
+        .clone()
+        .or_else(|| crate::config::cache_dir().map(|d| d.join("translations.sqlite3")));
+    let (cache, issue) = match path {
+        Some(path) => match TranslationCache::open(config, &path) {
+            Ok(cache) => (cache, None),
+            Err(e) => (
+                TranslationCache::memory_only(config),
+                Some(format!("translation cache {}: {}; using memory only", path.display(), e)),
+            ),
+        },
+        None => (TranslationCache::memory_only(config), None),
+    };
+    (Some(Arc::new(cache)), issue)
+}
+
+// The configured glossary, if any. A missing default file is not an error.
+pub fn load_glossary(config: &TranslationConfig) -> Result<Option<Arc<Glossary>>, String> {
+    let path = match &config.glossary {
+        Some(path) => path.clone(),
+        None => {
+            let default = crate::config::config_dir().and_then(|dir| {
+                ["glossary.tsv", "glossary.csv"].into_iter().map(|name| dir.join(name)).find(|p| p.exists())
+            });
+            match default {
+                Some(path) => path,
+                None => return Ok(None),
+            }
+        }
+    };
+    let glossary = Glossary::load(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Some(Arc::new(glossary)))
+}
+
+pub fn with_glossary(translator: Arc<dyn Translator>, glossary: Option<&Arc<Glossary>>) -> Arc<dyn Translator> {
+    match glossary {
+        Some(glossary) => Arc::new(GlossaryTranslator::new(translator, glossary.clone())),
+        None => translator,
+    }
+}
+
+pub fn with_cache(translator: Arc<dyn Translator>, cache: Option<&Arc<TranslationCache>>) -> Arc<dyn Translator> {
+    match cache {
+        Some(cache) => Arc::new(CachedTranslator::new(translator, cache.clone())),
+        None => translator,
+    }
+}
+
+fn http_client(timeout: Duration) -> reqwest::blocking::Client {
+    reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+// Send and turn transport failures and non-2xx statuses into typed errors
+fn send(request: reqwest::blocking::RequestBuilder) -> Result<reqwest::blocking::Response, TranslationError> {
+    let resp = request.send()?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return Err(TranslationError::RateLimited { retry_after });
+    }
+    // Keep a little of the body; providers usually explain auth/quota problems there
+    let body: String = resp.text().unwrap_or_default().chars().take(200).collect();
+    Err(TranslationError::Http { status: status.as_u16(), body: body.trim().to_string() })
+}
+
+fn unexpected(what: &str) -> TranslationError {
+    TranslationError::Parse(what.to_string())
+}
+
+fn join_url(base: &str, path: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+// English names for LLM prompts; codes we don't know are passed through as-is
+fn language_name(code: &str) -> &str {
+    match code.to_ascii_lowercase().as_str() {
+        "ja" => "Japanese",
+        "en" => "English",
+        "zh" | "zh-cn" => "Simplified Chinese",
+        "zh-tw" => "Traditional Chinese",
+        "ko" => "Korean",
+        "de" => "German",
+        "fr" => "French",
+        "es" => "Spanish",
+        _ => code,
+    }
+}
@@ -0,0 +1,60 @@
+use serde_json::json;
+
+use super::{join_url, language_name, send, unexpected, Translation, TranslationRequest, TranslationResult, Translator};
+
+// Any OpenAI-compatible /chat/completions endpoint (OpenAI, Azure proxies, Ollama, llama.cpp)
+pub struct OpenAiTranslator {
+    client: reqwest::blocking::Client,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAiTranslator {
+    pub fn new(client: reqwest::blocking::Client, base_url: &str, api_key: Option<String>, model: &str) -> Self {
+        Self {
+            client,
+            url: join_url(base_url, "chat/completions"),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+}
+
+impl Translator for OpenAiTranslator {
+    fn id(&self) -> &str {
+        "openai"
+    }
+
+    fn fingerprint(&self) -> String {
+        format!("{} {}", self.url, self.model)
+    }
+
+    fn translate(&self, request: &TranslationRequest) -> TranslationResult {
+        let instruction = format!(
+            "Translate the user's text into {}. Reply with the translation only, keeping line breaks.",
+            language_name(&request.target)
+        );
+        let body = json!({
+            "model": self.model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": instruction },
+                { "role": "user", "content": request.text },
+            ],
+        });
+
+        let mut req = self.client.post(&self.url).json(&body);
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let resp = send(req)?;
+        let json = resp.json::<serde_json::Value>()?;
+
+        // {"choices": [{"message": {"content": "..."}}]}
+        json["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|s| Translation::plain(s.trim()))
+            .ok_or_else(|| unexpected("no translated text in response"))
+    }
+}
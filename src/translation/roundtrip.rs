@@ -0,0 +1,55 @@
+// Back-translation check: translate, translate the result back, and compare with the original.
+// The score is a rough sanity signal, not a quality metric; it flags dropped or garbled sentences.
+
+use std::collections::HashMap;
+
+use super::Translation;
+
+#[derive(Debug, Clone)]
+pub struct RoundTrip {
+    pub original: String,
+    pub translation: Translation,
+    pub back: Translation,
+    // 0.0 (nothing in common) to 1.0 (identical after normalization)
+    pub similarity: f32,
+}
+
+impl RoundTrip {
+    pub fn new(original: String, translation: Translation, back: Translation) -> Self {
+        let similarity = similarity(&original, &back.text);
+        Self { original, translation, back, similarity }
+    }
+}
+
+// Dice coefficient over character bigrams of the lowercased text without spaces and punctuation.
+// Character bigrams work the same for Japanese (no word breaks) and English.
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let normalize = |text: &str| -> Vec<char> {
+        text.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return if a == b { 1.0 } else { 0.0 };
+    }
+
+    let mut counts: HashMap<(char, char), usize> = HashMap::new();
+    for pair in a.windows(2) {
+        *counts.entry((pair[0], pair[1])).or_default() += 1;
+    }
+    let mut shared = 0;
+    for pair in b.windows(2) {
+        if let Some(count) = counts.get_mut(&(pair[0], pair[1])) {
+            if *count > 0 {
+                *count -= 1;
+                shared += 1;
+            }
+        }
+    }
+    (2 * shared) as f32 / (a.len() - 1 + b.len() - 1) as f32
+}
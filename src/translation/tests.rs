@@ -0,0 +1,209 @@
+// The HTTP providers against a local stand-in server, through their configurable base URLs:
+// what each one sends, how it reads the answer, and how `send()` maps failures.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::Receiver;
+
+use super::*;
+
+struct Reply {
+    status: u16,
+    headers: Vec<(&'static str, &'static str)>,
+    body: String,
+}
+
+fn ok(body: &str) -> Reply {
+    Reply { status: 200, headers: Vec::new(), body: body.to_string() }
+}
+
+#[derive(Debug)]
+struct Received {
+    method: String,
+    // Path and query
+    target: String,
+    // Lowercase names
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Received {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+// Answers one connection per reply, in order, and passes on what it was sent
+fn serve(replies: Vec<Reply>) -> (String, Receiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = crossbeam_channel::unbounded();
+    thread::spawn(move || {
+        for reply in replies {
+            let Ok((mut stream, _)) = listener.accept() else { return };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_string();
+            let target = parts.next().unwrap_or_default().to_string();
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let Some((name, value)) = line.trim_end().split_once(':') else { break };
+                headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+            }
+            let length = headers.iter().find(|(n, _)| n == "content-length").map_or(0, |(_, v)| v.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let _ = tx.send(Received { method, target, headers, body: String::from_utf8(body).unwrap() });
+
+            let mut response = format!(
+                "HTTP/1.1 {} Reply\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                reply.status,
+                reply.body.len()
+            );
+            for (name, value) in reply.headers {
+                response.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            response.push_str("\r\n");
+            response.push_str(&reply.body);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (base_url, rx)
+}
+
+fn provider(kind: ProviderKind, base_url: &str) -> Arc<dyn Translator> {
+    let mut config = TranslationConfig { timeout_secs: 5, ..TranslationConfig::default() };
+    for provider in [&mut config.google, &mut config.deepl, &mut config.libretranslate, &mut config.openai] {
+        provider.base_url = base_url.to_string();
+        provider.api_key = Some("test-key".to_string());
+    }
+    config.openai.model = Some("test-model".to_string());
+    build(kind, &config).unwrap()
+}
+
+fn request(text: &str) -> TranslationRequest {
+    TranslationRequest {
+        text: text.to_string(),
+        source: None,
+        target: "ja".to_string(),
+        bypass_cache: false,
+        glossary: Vec::new(),
+        cancel: CancelToken::new(),
+    }
+}
+
+fn received(rx: &Receiver<Received>) -> Received {
+    rx.recv_timeout(Duration::from_secs(5)).expect("the provider sent nothing")
+}
+
+#[test]
+fn google_posts_the_text_and_reads_details() {
+    let answer = r#"[[["こんにちは","hello",null,null,10]],[["間投詞",["やあ","こんにちは"]]],"en"]"#;
+    let (base_url, rx) = serve(vec![ok(answer)]);
+    let translation = provider(ProviderKind::Google, &base_url).translate(&request("hello")).unwrap();
+    assert_eq!(translation.text, "こんにちは");
+    assert_eq!(translation.detected_source.as_deref(), Some("en"));
+    assert_eq!(translation.dictionary[0].terms, ["やあ", "こんにちは"]);
+
+    let sent = received(&rx);
+    assert_eq!(sent.method, "POST");
+    assert!(sent.target.starts_with("/translate_a/single?client=gtx&sl=auto&tl=ja"), "{}", sent.target);
+    assert_eq!(sent.body, "q=hello");
+}
+
+#[test]
+fn deepl_sends_the_key_and_languages() {
+    let answer = r#"{"translations":[{"detected_source_language":"EN","text":"こんにちは"}]}"#;
+    let (base_url, rx) = serve(vec![ok(answer)]);
+    let translation = provider(ProviderKind::DeepL, &base_url)
+        .translate(&TranslationRequest { source: Some("en".to_string()), ..request("hello") })
+        .unwrap();
+    assert_eq!(translation.text, "こんにちは");
+    assert_eq!(translation.detected_source.as_deref(), Some("en"));
+
+    let sent = received(&rx);
+    assert_eq!(sent.target, "/v2/translate");
+    assert_eq!(sent.header("authorization"), Some("DeepL-Auth-Key test-key"));
+    assert_eq!(sent.body, "text=hello&target_lang=JA&source_lang=EN");
+}
+
+#[test]
+fn libretranslate_sends_json_with_the_key() {
+    let answer = r#"{"translatedText":"こんにちは","detectedLanguage":{"language":"en"}}"#;
+    let (base_url, rx) = serve(vec![ok(answer)]);
+    let translation = provider(ProviderKind::LibreTranslate, &base_url).translate(&request("hello")).unwrap();
+    assert_eq!(translation.text, "こんにちは");
+    assert_eq!(translation.detected_source.as_deref(), Some("en"));
+
+    let sent = received(&rx);
+    assert_eq!(sent.target, "/translate");
+    let body = sent.json();
+    assert_eq!(body["q"], "hello");
+    assert_eq!(body["source"], "auto");
+    assert_eq!(body["target"], "ja");
+    assert_eq!(body["api_key"], "test-key");
+}
+
+#[test]
+fn openai_asks_the_model_and_trims_the_reply() {
+    let answer = r#"{"choices":[{"message":{"role":"assistant","content":" こんにちは\n"}}]}"#;
+    let (base_url, rx) = serve(vec![ok(answer)]);
+    let translation = provider(ProviderKind::OpenAi, &base_url).translate(&request("hello")).unwrap();
+    assert_eq!(translation.text, "こんにちは");
+
+    let sent = received(&rx);
+    assert_eq!(sent.target, "/chat/completions");
+    assert_eq!(sent.header("authorization"), Some("Bearer test-key"));
+    let body = sent.json();
+    assert_eq!(body["model"], "test-model");
+    assert!(body["messages"][0]["content"].as_str().unwrap().contains("Japanese"));
+    assert_eq!(body["messages"][1]["content"], "hello");
+}
+
+#[test]
+fn rate_limits_carry_retry_after() {
+    let (base_url, _rx) = serve(vec![Reply { status: 429, headers: vec![("Retry-After", "30")], body: String::new() }]);
+    let error = provider(ProviderKind::DeepL, &base_url).translate(&request("hello")).unwrap_err();
+    assert_eq!(error, TranslationError::RateLimited { retry_after: Some(Duration::from_secs(30)) });
+}
+
+#[test]
+fn other_statuses_keep_the_start_of_the_body() {
+    let body = format!(r#"{{"message":"Quota exceeded"}}{}"#, "x".repeat(300));
+    let (base_url, _rx) = serve(vec![Reply { status: 456, headers: Vec::new(), body }]);
+    match provider(ProviderKind::LibreTranslate, &base_url).translate(&request("hello")).unwrap_err() {
+        TranslationError::Http { status, body } => {
+            assert_eq!(status, 456);
+            assert!(body.starts_with(r#"{"message":"Quota exceeded"}"#));
+            assert!(body.chars().count() <= 200);
+        }
+        other => panic!("expected an HTTP error, got {:?}", other),
+    }
+}
+
+#[test]
+fn unexpected_answers_are_parse_errors() {
+    let (base_url, _rx) = serve(vec![ok(r#"{"choices":[]}"#), ok("<html>")]);
+    let openai = provider(ProviderKind::OpenAi, &base_url);
+    assert!(matches!(openai.translate(&request("hello")), Err(TranslationError::Parse(_))));
+    assert!(matches!(openai.translate(&request("hello")), Err(TranslationError::Parse(_))));
+}
+
+#[test]
+fn unreachable_servers_are_offline() {
+    // Bound and dropped, so nothing listens there
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let google = provider(ProviderKind::Google, &format!("http://127.0.0.1:{}", port));
+    assert!(matches!(google.translate(&request("hello")), Err(TranslationError::Offline(_))));
+}
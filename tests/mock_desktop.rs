@@ -0,0 +1,81 @@
+// The whole pipeline on the mock platform: a scripted drag is recognized, the text is fetched
+// with the Ctrl+C probe, and the toolbar's Copy and Paste act on the desktop.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use popwin::actions::{ActionContext, ActionRegistry, CancelToken, RequestId, Selection};
+use popwin::config::Config;
+use popwin::hooks::gesture::InputEvent;
+use popwin::platform::mock::{FakeClipboard, MockDesktop, ScriptStep, ScriptedSelectionSource};
+use popwin::platform::{self, ClipboardSnapshot, SelectionSource, Shortcut};
+use popwin::AppEvent;
+
+fn text_of(snapshot: &ClipboardSnapshot) -> Option<String> {
+    let (_, data) = snapshot.formats.iter().find(|(format, _)| *format == FakeClipboard::TEXT_FORMAT)?;
+    String::from_utf8(data.clone()).ok()
+}
+
+#[test]
+fn drag_selection_then_copy_and_paste() {
+    // Keep the user's config, glossary, history and translation cache out of it
+    let dir = tempfile::tempdir().unwrap();
+    std::env::set_var("POPWIN_CONFIG_DIR", dir.path());
+    let mut config = Config::default();
+    config.translation.cache.enabled = false;
+    config.translation.cache.path = Some(dir.path().join("translations.sqlite3"));
+
+    let desktop = MockDesktop::new();
+    platform::install(Arc::new(desktop.injector()), Box::new(desktop.clipboard()));
+    desktop.clipboard().put_text("copied earlier");
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let mut source = ScriptedSelectionSource::new(
+        desktop.clone(),
+        vec![
+            ScriptStep::Input(InputEvent::Move { x: 140, y: 200 }),
+            ScriptStep::Input(InputEvent::ButtonDown { x: 140, y: 200 }),
+            ScriptStep::Select("hello world".to_string()),
+            ScriptStep::Wait(Duration::from_millis(150)),
+            ScriptStep::Input(InputEvent::Move { x: 220, y: 200 }),
+            ScriptStep::Input(InputEvent::ButtonUp { x: 220, y: 200 }),
+        ],
+    );
+    source.start(tx.clone()).unwrap();
+
+    let (text, position, app) = loop {
+        match rx.recv_timeout(Duration::from_secs(5)).expect("no selection detected") {
+            AppEvent::SelectionDetected { text, position, app } => break (text, position, app),
+            AppEvent::SelectionCleared => {}
+            other => panic!("unexpected event {:?}", other),
+        }
+    };
+    assert_eq!(text, "hello world");
+    assert_eq!(position, (220, 200));
+    // The probe's copy is gone and what the user had copied is back
+    assert_eq!(desktop.sent(), vec![Shortcut::Copy]);
+    assert_eq!(text_of(&desktop.clipboard().contents()).as_deref(), Some("copied earlier"));
+
+    let (registry, _) = ActionRegistry::with_builtins(&config);
+    let selection = Selection { app, ..Selection::new(text, position) };
+    let cancel = CancelToken::new();
+    let run = |id: &str| {
+        let ctx = ActionContext {
+            selection: &selection,
+            sender: &tx,
+            cancel: &cancel,
+            request_id: RequestId::next(),
+            option: None,
+            force_refresh: false,
+        };
+        registry.get(id).expect(id).run(&ctx);
+    };
+
+    run("copy");
+    assert_eq!(text_of(&desktop.clipboard().contents()).as_deref(), Some("hello world"));
+
+    desktop.select("goodbye");
+    run("paste");
+    assert_eq!(desktop.sent(), vec![Shortcut::Copy, Shortcut::Paste]);
+    assert_eq!(desktop.selection(), "hello world");
+}